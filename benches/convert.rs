@@ -0,0 +1,73 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use convert_typst_math_to_mathml::{converter::Converter, eval_math::evict_cache};
+
+/// An input whose evaluation takes far longer than converting its result.
+const EXPENSIVE: &str = "$ x = #range(200000).fold(0, (sum, i) => sum + i) $";
+
+/// A matrix with `rows` rows of 4 columns.
+fn mat_input(rows: usize) -> String {
+    let rows = (0..rows)
+        .map(|row| format!("x_{row}, y^{row}, {row}/2, sqrt({row})"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    format!("$ mat({}) $", rows)
+}
+
+/// A `cases` with `rows` branches.
+fn cases_input(rows: usize) -> String {
+    let rows = (0..rows)
+        .map(|row| format!("{row} x^2 & \"if\" x = {row}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("$ f(x) = cases({}) $", rows)
+}
+
+fn bench_large(c: &mut Criterion, name: &str, input: fn(usize) -> String) {
+    let converter = Converter::default();
+    let mut group = c.benchmark_group(name);
+    let mut out = String::new();
+
+    for rows in [50, 100, 200, 400, 800] {
+        let input = input(rows);
+        group.throughput(Throughput::Elements(rows as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(rows), &input, |b, input| {
+            b.iter(|| {
+                out.clear();
+                converter.convert_into(input, &mut out).unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_mat(c: &mut Criterion) {
+    bench_large(c, "mat", mat_input);
+}
+
+fn bench_cases(c: &mut Criterion) {
+    bench_large(c, "cases", cases_input);
+}
+
+/// Converting an input again reuses its memoized evaluation, unless the cache was
+/// evicted in between.
+fn bench_cache(c: &mut Criterion) {
+    let converter = Converter::default();
+    let mut group = c.benchmark_group("cache");
+
+    group.bench_function("memoized", |b| {
+        b.iter(|| converter.convert(EXPENSIVE).unwrap())
+    });
+    group.bench_function("evicted", |b| {
+        b.iter(|| {
+            evict_cache(0);
+            converter.convert(EXPENSIVE).unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_mat, bench_cases, bench_cache);
+criterion_main!(benches);
@@ -0,0 +1,263 @@
+//! Convert Typst equations to MathML from the command line.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, ConvertedDocument, Converter, DisplayMode},
+    diagnostic::{ConvertError, Diagnostic, Location, Origin, Severity},
+};
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+Usage: typst-mathml [OPTIONS] [FILE]...
+
+Convert every equation in the Typst FILEs, or in the standard input if no FILE
+is given or FILE is `-`, to MathML. Each equation is written on its own line.
+
+Options:
+  -i, --inline             Render all equations inline
+  -b, --block              Render all equations as blocks
+  -a, --annotation         Attach the Typst source as an annotation
+  -p, --preamble <FILE>    Evaluate FILE once and use its bindings in every input
+  -o, --output <FILE>      Write to FILE instead of the standard output
+      --json               Write JSON including diagnostics
+  -h, --help               Print this help
+
+The exit code is 1 if any input fails to convert and 2 for invalid arguments.";
+
+#[derive(Debug, Default)]
+struct Args {
+    display: DisplayMode,
+    annotation: bool,
+    preamble: Option<String>,
+    output: Option<String>,
+    json: bool,
+    inputs: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    let mut only_inputs = false;
+
+    while let Some(arg) = args.next() {
+        if only_inputs || arg == "-" || !arg.starts_with('-') {
+            parsed.inputs.push(arg);
+            continue;
+        }
+
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{}`", name))
+        };
+        match arg.as_str() {
+            "-i" | "--inline" => parsed.display = DisplayMode::Inline,
+            "-b" | "--block" => parsed.display = DisplayMode::Block,
+            "-a" | "--annotation" => parsed.annotation = true,
+            "-p" | "--preamble" => parsed.preamble = Some(value(&arg)?),
+            "-o" | "--output" => parsed.output = Some(value(&arg)?),
+            "--json" => parsed.json = true,
+            "-h" | "--help" => return Ok(None),
+            "--" => only_inputs = true,
+            _ => return Err(format!("unknown option `{}`", arg)),
+        }
+    }
+
+    if parsed.inputs.is_empty() {
+        parsed.inputs.push("-".to_string());
+    }
+
+    Ok(Some(parsed))
+}
+
+fn read_input(name: &str) -> Result<String, ConvertError> {
+    if name == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| ConvertError::Io(format!("<stdin>: {}", err)))?;
+        Ok(text)
+    } else {
+        fs::read_to_string(name).map_err(|err| ConvertError::Io(format!("{}: {}", name, err)))
+    }
+}
+
+fn convert(converter: &Converter, name: &str) -> Result<ConvertedDocument, ConvertError> {
+    let document = converter.convert_all(&read_input(name)?)?;
+    if document.equations.is_empty() {
+        return Err(ConvertError::NoEquation);
+    }
+
+    Ok(document)
+}
+
+fn location_json(location: Option<&Location>) -> Value {
+    match location {
+        Some(location) => json!({
+            "origin": match location.origin {
+                Origin::Input => "input",
+                Origin::Preamble => "preamble",
+            },
+            "range": { "start": location.range.start, "end": location.range.end },
+            "start": { "line": location.start.line, "column": location.start.column },
+            "end": { "line": location.end.line, "column": location.end.column },
+        }),
+        None => Value::Null,
+    }
+}
+
+fn diagnostic_json(diagnostic: &Diagnostic) -> Value {
+    json!({
+        "severity": match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        "message": diagnostic.message,
+        "hints": diagnostic.hints,
+        "location": location_json(diagnostic.location.as_ref()),
+        "trace": diagnostic.trace.iter().map(|point| json!({
+            "message": point.message,
+            "location": location_json(point.location.as_ref()),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn error_json(err: &ConvertError) -> Vec<Value> {
+    match err {
+        ConvertError::Compile(errors) | ConvertError::Preamble(errors) => {
+            errors.iter().map(diagnostic_json).collect()
+        }
+        ConvertError::UnsupportedElement { location, .. } => vec![json!({
+            "severity": "error",
+            "message": err.to_string(),
+            "hints": [],
+            "location": location_json(location.as_ref()),
+        })],
+        ConvertError::Io(_) | ConvertError::NoEquation => vec![json!({
+            "severity": "error",
+            "message": err.to_string(),
+            "hints": [],
+            "location": Value::Null,
+        })],
+    }
+}
+
+fn result_json(name: &str, result: &Result<ConvertedDocument, ConvertError>) -> Value {
+    match result {
+        Ok(document) => json!({
+            "input": name,
+            "equations": document.equations.iter().map(|equation| json!({
+                "mathml": equation.mathml,
+                "block": equation.block,
+                "label": equation.label,
+                "location": location_json(equation.location.as_ref()),
+            })).collect::<Vec<_>>(),
+            "diagnostics": document.warnings.iter().map(diagnostic_json).collect::<Vec<_>>(),
+        }),
+        Err(err) => json!({
+            "input": name,
+            "equations": [],
+            "diagnostics": error_json(err),
+        }),
+    }
+}
+
+fn run(args: Args) -> Result<bool, ConvertError> {
+    let mut converter = Converter::new(ConvertOptions {
+        display: args.display,
+        annotation: args.annotation,
+        ..Default::default()
+    });
+    let preamble = match &args.preamble {
+        Some(preamble) => converter.set_preamble_file(preamble),
+        None => Ok(Vec::new()),
+    };
+    // Without `--json`, errors in the preamble are printed like other fatal errors.
+    if let (Err(err), false) = (&preamble, args.json) {
+        return Err(err.clone());
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            fs::File::create(path).map_err(|err| ConvertError::Io(format!("{}: {}", path, err)))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let write_err = |err: io::Error| ConvertError::Io(err.to_string());
+
+    // None of the inputs is converted if the preamble fails.
+    let preamble_warnings = match preamble {
+        Ok(warnings) => warnings,
+        Err(err) => {
+            let json = json!({
+                "preamble": error_json(&err),
+                "results": [],
+            });
+            writeln!(out, "{:#}", json).map_err(write_err)?;
+            out.flush().map_err(write_err)?;
+            return Ok(false);
+        }
+    };
+
+    let mut success = true;
+    let mut results = Vec::new();
+    for name in &args.inputs {
+        let result = convert(&converter, name);
+        success &= result.is_ok();
+
+        if args.json {
+            results.push(result_json(name, &result));
+            continue;
+        }
+        match result {
+            Ok(document) => {
+                for warning in &document.warnings {
+                    eprintln!("{}: {}", name, warning);
+                }
+                for equation in &document.equations {
+                    writeln!(out, "{}", equation.mathml).map_err(write_err)?;
+                }
+            }
+            Err(err) => eprintln!("{}: {}", name, err),
+        }
+    }
+
+    if args.json {
+        let json = json!({
+            "preamble": preamble_warnings.iter().map(diagnostic_json).collect::<Vec<_>>(),
+            "results": results,
+        });
+        writeln!(out, "{:#}", json).map_err(write_err)?;
+    } else {
+        for warning in &preamble_warnings {
+            eprintln!("preamble: {}", warning);
+        }
+    }
+    out.flush().map_err(write_err)?;
+
+    Ok(success)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use typst::foundations::{Content, StyleChain};
+
+use crate::{
+    diagnostic::{ConvertError, Diagnostic, Location, Origin},
+    eval_math::{eval_document, eval_with_preamble, Preamble},
+    handler::ElementHandlers,
+    mathml::{self, Converted},
+    node::{Element, Node},
+};
+
+/// Whether the equation is rendered inline or as a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayMode {
+    /// Follow the equation, `$x$` is inline and `$ x $` is a block.
+    #[default]
+    Auto,
+    Inline,
+    Block,
+}
+
+/// What to emit for Typst elements that have no MathML counterpart yet.
+///
+/// Whatever the policy, the names of these elements are counted in the
+/// `unsupported` summary of the conversion result.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum UnknownElementPolicy {
+    /// Emit `<merror>` naming the element.
+    #[default]
+    Merror,
+    /// Fail with [`ConvertError::UnsupportedElement`].
+    Error,
+    /// Leave the element out of the output.
+    Skip,
+    /// Ask a callback for the replacement, `None` leaves the element out.
+    Callback(UnknownElementCallback),
+}
+
+/// Fallback for [`UnknownElementPolicy::Callback`].
+///
+/// ```no_run
+/// use convert_typst_math_to_mathml::converter::{
+///     ConvertOptions, UnknownElementCallback, UnknownElementPolicy,
+/// };
+/// use convert_typst_math_to_mathml::node::Element;
+///
+/// let options = ConvertOptions {
+///     unknown_element: UnknownElementPolicy::Callback(UnknownElementCallback::new(
+///         |content| Some(Element::new("mtext").with_text(content.plain_text()).into()),
+///     )),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct UnknownElementCallback(Arc<dyn Fn(&Content) -> Option<Node> + Send + Sync>);
+
+impl UnknownElementCallback {
+    pub fn new(callback: impl Fn(&Content) -> Option<Node> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn call(&self, content: &Content) -> Option<Node> {
+        (self.0)(content)
+    }
+}
+
+impl Debug for UnknownElementCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("UnknownElementCallback(..)")
+    }
+}
+
+/// Callbacks are equal if they are clones of each other.
+impl PartialEq for UnknownElementCallback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for UnknownElementCallback {}
+
+/// The MathML consumer the output is tailored for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetProfile {
+    /// MathML Core as implemented by browsers, using CSS where browsers ignore
+    /// MathML attributes or elements.
+    #[default]
+    Core,
+    /// Full MathML 3, e.g. for MathJax, using elements and attributes outside
+    /// of MathML Core such as `menclose` and `columnalign`.
+    Full,
+}
+
+/// Who decides the spacing around operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpacingMode {
+    /// The operator dictionary of the renderer, which differs between browsers
+    /// and MathJax.
+    #[default]
+    Renderer,
+    /// The spacing Typst puts between math classes, written as explicit `lspace`
+    /// and `rspace` on operators and `<mspace>` between other atoms.
+    Typst,
+}
+
+/// The decimal separator of numbers, the other one of dot and comma separates
+/// digit groups, e.g. `1,000.5` or `1.000,5`. Spaces separate digit groups with
+/// either, e.g. `1 000`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecimalSeparator {
+    #[default]
+    Dot,
+    Comma,
+}
+
+/// Configuration of a [`Converter`].
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub display: DisplayMode,
+    /// Attach the Typst source as an `<annotation>` of the `<semantics>` element.
+    pub annotation: bool,
+    /// Declare the MathML namespace on the `<math>` element, needed when the
+    /// output is embedded in XHTML or processed as XML.
+    pub xmlns: bool,
+    /// Prefix of the CSS classes used by polyfills, e.g. `{prefix}-cancel`.
+    pub class_prefix: String,
+    pub unknown_element: UnknownElementPolicy,
+    pub profile: TargetProfile,
+    pub spacing: SpacingMode,
+    pub decimal_separator: DecimalSeparator,
+    /// Custom conversions, consulted before the built-in ones.
+    pub handlers: ElementHandlers,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            display: DisplayMode::Auto,
+            annotation: false,
+            xmlns: false,
+            class_prefix: "equation-typst".to_string(),
+            unknown_element: UnknownElementPolicy::Merror,
+            profile: TargetProfile::Core,
+            spacing: SpacingMode::Renderer,
+            decimal_separator: DecimalSeparator::Dot,
+            handlers: ElementHandlers::default(),
+        }
+    }
+}
+
+/// An equation converted to a MathML tree by [`Converter::convert_to_tree`].
+#[derive(Debug, Clone)]
+pub struct ConvertedTree {
+    /// The `<math>` element.
+    pub math: Element,
+    /// Warnings emitted by the Typst compiler, the conversion still succeeded.
+    pub warnings: Vec<Diagnostic>,
+    /// How often each element without MathML counterpart was encountered.
+    pub unsupported: BTreeMap<String, usize>,
+}
+
+/// One equation of a document converted by [`Converter::convert_all`].
+#[derive(Debug, Clone)]
+pub struct ConvertedEquation {
+    /// Whether the equation is displayed as a block, after applying
+    /// [`ConvertOptions::display`].
+    pub block: bool,
+    /// Where the equation is written, `None` if it was created without a span.
+    pub location: Option<Location>,
+    /// The label attached to the equation, without angle brackets.
+    pub label: Option<String>,
+    pub mathml: String,
+}
+
+/// All equations of a document converted by [`Converter::convert_all`].
+#[derive(Debug, Clone)]
+pub struct ConvertedDocument {
+    /// The equations in document order.
+    pub equations: Vec<ConvertedEquation>,
+    /// Warnings emitted by the Typst compiler, the conversion still succeeded.
+    pub warnings: Vec<Diagnostic>,
+    /// How often each element without MathML counterpart was encountered, summed
+    /// over all equations.
+    pub unsupported: BTreeMap<String, usize>,
+}
+
+/// Converts Typst equations to MathML with a fixed set of options.
+///
+/// ```no_run
+/// use convert_typst_math_to_mathml::converter::{ConvertOptions, Converter, DisplayMode};
+///
+/// let converter = Converter::new(ConvertOptions {
+///     display: DisplayMode::Inline,
+///     ..Default::default()
+/// });
+/// for input in ["$x^2$", "$ sum_(i=1)^n i $"] {
+///     println!("{}", converter.convert(input).unwrap().mathml);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Converter {
+    options: ConvertOptions,
+    preamble: Option<Preamble>,
+}
+
+impl Converter {
+    pub fn new(options: ConvertOptions) -> Self {
+        Self {
+            options,
+            preamble: None,
+        }
+    }
+
+    pub fn options(&self) -> &ConvertOptions {
+        &self.options
+    }
+
+    /// Evaluate `text` once and bring its bindings into scope for every following
+    /// conversion, replacing any previous preamble.
+    ///
+    /// Errors in the preamble are reported as [`ConvertError::Preamble`], the
+    /// returned warnings come from the preamble as well.
+    pub fn set_preamble(&mut self, text: &str) -> Result<Vec<Diagnostic>, ConvertError> {
+        let preamble = Preamble::new(text)?;
+        let warnings = preamble.warnings().to_vec();
+        self.preamble = Some(preamble);
+
+        Ok(warnings)
+    }
+
+    /// Like [`Converter::set_preamble`], reading the preamble from `path`.
+    pub fn set_preamble_file(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<Diagnostic>, ConvertError> {
+        let preamble = Preamble::from_file(path)?;
+        let warnings = preamble.warnings().to_vec();
+        self.preamble = Some(preamble);
+
+        Ok(warnings)
+    }
+
+    pub fn clear_preamble(&mut self) {
+        self.preamble = None;
+    }
+
+    pub fn preamble(&self) -> Option<&Preamble> {
+        self.preamble.as_ref()
+    }
+
+    /// Convert the first equation in `content` to MathML.
+    pub fn convert(&self, content: &str) -> Result<Converted, ConvertError> {
+        let tree = self.convert_to_tree(content)?;
+
+        Ok(Converted {
+            mathml: tree.math.to_string(),
+            warnings: tree.warnings,
+            unsupported: tree.unsupported,
+        })
+    }
+
+    /// Convert the first equation in `content` to a MathML tree, which can be
+    /// rewritten with a [`VisitorMut`](crate::node::VisitorMut) before serializing it.
+    pub fn convert_to_tree(&self, content: &str) -> Result<ConvertedTree, ConvertError> {
+        let evaluated = eval_with_preamble(content, self.preamble.as_ref())?;
+        let mut unsupported = BTreeMap::new();
+        let math = mathml::convert_equation(
+            &self.options,
+            content,
+            &evaluated.equation,
+            evaluated.styles.as_ref(),
+            evaluated.world(),
+            &mut unsupported,
+        )?;
+
+        Ok(ConvertedTree {
+            math,
+            warnings: evaluated.warnings,
+            unsupported,
+        })
+    }
+
+    /// Convert the first equation in `content`, writing the MathML into `out`
+    /// instead of returning it, e.g. to reuse one buffer for many equations.
+    ///
+    /// Returns the warnings of the Typst compiler.
+    pub fn convert_into(
+        &self,
+        content: &str,
+        out: &mut impl fmt::Write,
+    ) -> Result<Vec<Diagnostic>, ConvertError> {
+        let tree = self.convert_to_tree(content)?;
+        tree.math
+            .write_to(out)
+            .map_err(|_| ConvertError::Io("failed to write the MathML output".to_string()))?;
+
+        Ok(tree.warnings)
+    }
+
+    /// Like [`Converter::convert_into`], writing UTF-8 into a byte sink such as a
+    /// file or a socket.
+    pub fn convert_into_io(
+        &self,
+        content: &str,
+        out: &mut impl io::Write,
+    ) -> Result<Vec<Diagnostic>, ConvertError> {
+        let tree = self.convert_to_tree(content)?;
+        tree.math
+            .write_io(out)
+            .map_err(|err| ConvertError::Io(err.to_string()))?;
+
+        Ok(tree.warnings)
+    }
+
+    /// Convert every equation in `content`, evaluating the document only once.
+    ///
+    /// The annotation of each equation, if enabled, is its own source text.
+    pub fn convert_all(&self, content: &str) -> Result<ConvertedDocument, ConvertError> {
+        let mut document = eval_document(content, self.preamble.as_ref())?;
+        let mut unsupported = BTreeMap::new();
+
+        let mut equations = Vec::with_capacity(document.equations.len());
+        for evaluated in std::mem::take(&mut document.equations) {
+            let source = match &evaluated.location {
+                Some(location) if location.origin == Origin::Input => {
+                    &content[location.range.clone()]
+                }
+                _ => "",
+            };
+            let math = mathml::convert_equation(
+                &self.options,
+                source,
+                &evaluated.equation,
+                Some(&evaluated.styles),
+                document.world(),
+                &mut unsupported,
+            )?;
+
+            equations.push(ConvertedEquation {
+                block: mathml::is_block(
+                    &self.options,
+                    &evaluated.equation,
+                    StyleChain::new(&evaluated.styles),
+                ),
+                mathml: math.to_string(),
+                location: evaluated.location,
+                label: evaluated.label,
+            });
+        }
+
+        Ok(ConvertedDocument {
+            equations,
+            warnings: document.warnings,
+            unsupported,
+        })
+    }
+}
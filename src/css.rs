@@ -0,0 +1,15 @@
+//! CSS needed to render the output of [`TargetProfile::Core`] in browsers.
+//!
+//! [`TargetProfile::Core`]: crate::converter::TargetProfile::Core
+
+/// The class prefix used in [`POLYFILL`].
+const DEFAULT_PREFIX: &str = "equation-typst";
+
+/// Polyfills for the default class prefix `equation-typst`.
+pub const POLYFILL: &str = include_str!("polyfill.css");
+
+/// Polyfills for the classes generated with `class_prefix`, see
+/// [`ConvertOptions::class_prefix`](crate::converter::ConvertOptions::class_prefix).
+pub fn polyfill(class_prefix: &str) -> String {
+    POLYFILL.replace(DEFAULT_PREFIX, class_prefix)
+}
@@ -0,0 +1,186 @@
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use typst::diag::{self, SourceDiagnostic};
+use typst::syntax::{Source, Span};
+
+/// Error returned when the input can not be converted to MathML.
+#[derive(Debug, Clone)]
+pub enum ConvertError {
+    /// The Typst compiler rejected the input.
+    Compile(Vec<Diagnostic>),
+    /// The Typst compiler rejected the preamble.
+    Preamble(Vec<Diagnostic>),
+    /// Reading the input or writing the output failed.
+    Io(String),
+    /// The input was evaluated successfully, but it does not contain any equation.
+    NoEquation,
+    /// The equation contains an element without MathML counterpart and
+    /// [`UnknownElementPolicy::Error`](crate::converter::UnknownElementPolicy::Error)
+    /// is set.
+    UnsupportedElement {
+        name: String,
+        location: Option<Location>,
+    },
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compile(errors) | Self::Preamble(errors) => {
+                for (idx, error) in errors.iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            Self::NoEquation => write!(f, "no equation found in the input"),
+            Self::Io(message) => write!(f, "{}", message),
+            Self::UnsupportedElement { name, location } => {
+                write!(f, "error")?;
+                if let Some(location) = location {
+                    write!(f, " at {}", location)?;
+                }
+                write!(f, ": `{}` is not supported", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Whether a diagnostic is an error or a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A zero-based line and column in the input, the column is counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The text a [`Location`] refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// The input passed to the converter.
+    Input,
+    /// The preamble registered on the converter.
+    Preamble,
+}
+
+/// Where a diagnostic points to in the input or in the preamble.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub origin: Origin,
+    /// The byte range in the text of `origin`.
+    pub range: Range<usize>,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// One step of the call trace that led to a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TracePoint {
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+/// A message reported by the Typst compiler, with its span resolved against the input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub hints: Vec<String>,
+    /// `None` if the span is detached or points outside of the input.
+    pub location: Option<Location>,
+    pub trace: Vec<TracePoint>,
+}
+
+impl Diagnostic {
+    pub(crate) fn from_typst(
+        diagnostic: &SourceDiagnostic,
+        locate: impl Fn(Span) -> Option<Location>,
+    ) -> Self {
+        Self {
+            severity: match diagnostic.severity {
+                diag::Severity::Error => Severity::Error,
+                diag::Severity::Warning => Severity::Warning,
+            },
+            message: diagnostic.message.to_string(),
+            hints: diagnostic
+                .hints
+                .iter()
+                .map(|hint| hint.to_string())
+                .collect(),
+            location: locate(diagnostic.span),
+            trace: diagnostic
+                .trace
+                .iter()
+                .map(|point| TracePoint {
+                    message: point.v.to_string(),
+                    location: locate(point.span),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}", severity)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        write!(f, ": {}", self.message)?;
+        for hint in &self.hints {
+            write!(f, "\n  hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Location {
+    /// One-based line and column, e.g. `3:5` or `preamble 3:5`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let origin = match self.origin {
+            Origin::Input => "",
+            Origin::Preamble => "preamble ",
+        };
+        write!(
+            f,
+            "{}{}:{}",
+            origin,
+            self.start.line + 1,
+            self.start.column + 1
+        )
+    }
+}
+
+impl Location {
+    /// Resolve `range` of `source`, which is the text of `origin`.
+    pub(crate) fn resolve(source: &Source, range: Range<usize>, origin: Origin) -> Option<Self> {
+        let position = |byte: usize| {
+            Some(Position {
+                line: source.byte_to_line(byte)?,
+                column: source.byte_to_column(byte)?,
+            })
+        };
+
+        Some(Self {
+            origin,
+            start: position(range.start)?,
+            end: position(range.end)?,
+            range,
+        })
+    }
+}
@@ -1,58 +1,241 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
 use typst::comemo::{Track, Tracked};
-use typst::diag::{FileError, FileResult, SourceResult};
+use typst::diag::{At, FileError, FileResult, SourceDiagnostic, SourceResult};
 use typst::ecow::EcoString;
-use typst::engine::{Route, Sink, Traced};
+use typst::engine::{Engine, Route, Sink, Traced};
 use typst::foundations::{
-    Bytes, Datetime, Module, NativeElement, Packed, Selector, StyledElem, Styles,
+    Bytes, Content, Context, Datetime, FromValue, Func, IntoArgs, Module, NativeElement, Packed,
+    Repr, Selector, SequenceElem, StyleChain, StyledElem, Styles,
 };
+use typst::introspection::Introspector;
 use typst::math::EquationElem;
-use typst::syntax::{FileId, Source};
+use typst::syntax::{FileId, Source, Span, SyntaxKind, VirtualPath};
 use typst::text::{Font, FontBook};
 use typst::utils::LazyHash;
 use typst::{Library, World, ROUTINES};
 
+use crate::diagnostic::{ConvertError, Diagnostic, Location, Origin};
+
+/// Path of the input in the virtual file system of [`TypstWrapperWorld`].
+///
+/// Every input has the same path, so that the memoized evaluation of an input is
+/// reused when the same input is evaluated again. A detached source would get a new
+/// file id for every input, which neither hits the cache nor lasts, as there are only
+/// 2^16 file ids.
+const MAIN_PATH: &str = "/main.typ";
+
+/// Path of the preamble in the virtual file system of [`TypstWrapperWorld`].
+const PREAMBLE_PATH: &str = "/preamble.typ";
+
+/// The first equation of an evaluated input.
 #[derive(Debug)]
-pub enum EvalMathResult {
-    CompilerInnerError,
-    NoEquationError,
+pub struct EvaluatedMath {
+    pub equation: Packed<EquationElem>,
+    /// Styles set by show or set rules before the equation.
+    pub styles: Option<Styles>,
+    /// Warnings emitted by the compiler during evaluation.
+    pub warnings: Vec<Diagnostic>,
+    world: TypstWrapperWorld,
 }
 
-pub fn eval(content: &str) -> Result<(Packed<EquationElem>, Option<Styles>), EvalMathResult> {
-    let traced = Traced::default();
-    let world = TypstWrapperWorld::new(content.to_string());
+impl EvaluatedMath {
+    /// Resolve the span of an element of the equation to its location.
+    pub fn locate(&self, span: Span) -> Option<Location> {
+        self.world.locate(span)
+    }
 
-    let eval_res = match eval_impl(&world, traced.track()) {
-        Ok(res) => res,
-        Err(_) => return Err(EvalMathResult::CompilerInnerError),
-    };
+    pub(crate) fn world(&self) -> &TypstWrapperWorld {
+        &self.world
+    }
+}
 
-    let typst_content = eval_res.content();
-    let styles = if typst_content.elem().name() == "styled" {
-        let coerced = typst_content
-            .to_packed::<StyledElem>()
-            .expect("Type conversion to `StyledElem` must be successful.");
-        Some(coerced.styles().to_owned())
-    } else {
-        None
-    };
+/// All equations of an evaluated input.
+#[derive(Debug)]
+pub struct EvaluatedDocument {
+    pub equations: Vec<EvaluatedEquation>,
+    /// Warnings emitted by the compiler during evaluation.
+    pub warnings: Vec<Diagnostic>,
+    world: TypstWrapperWorld,
+}
+
+impl EvaluatedDocument {
+    /// Resolve the span of an element of an equation to its location.
+    pub fn locate(&self, span: Span) -> Option<Location> {
+        self.world.locate(span)
+    }
+
+    pub(crate) fn world(&self) -> &TypstWrapperWorld {
+        &self.world
+    }
+}
+
+#[derive(Debug)]
+pub struct EvaluatedEquation {
+    pub equation: Packed<EquationElem>,
+    /// Styles set by the set rules surrounding the equation.
+    pub styles: Styles,
+    /// Where the equation is written, `None` if it was created without a span.
+    pub location: Option<Location>,
+    /// The label attached to the equation, without angle brackets.
+    pub label: Option<String>,
+}
 
-    let typst_elem = match typst_content.query_first(&Selector::Elem(EquationElem::elem(), None)) {
-        Some(content) => content,
-        None => return Err(EvalMathResult::NoEquationError),
+/// Typst source whose bindings are in scope for every equation, e.g. a file of
+/// `#let` macros.
+///
+/// The preamble is evaluated once when it is created, and its bindings are added to
+/// the global and math scopes of a copy of the standard library, which the worlds of
+/// the equations use instead of the shared one.
+#[derive(Debug, Clone)]
+pub struct Preamble {
+    source: Source,
+    library: Arc<LazyHash<Library>>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Preamble {
+    /// Evaluate `text` as a preamble, errors are reported as [`ConvertError::Preamble`].
+    pub fn new(text: impl Into<String>) -> Result<Self, ConvertError> {
+        let source = Source::new(preamble_id(), text.into());
+        let world = TypstWrapperWorld::with_main(source.clone(), None);
+        let (module, warnings) = eval_world(&world).map_err(ConvertError::Preamble)?;
+
+        let mut library = Library::clone(&LIBRARY);
+        for (name, value, _) in module.scope().iter() {
+            library
+                .global
+                .scope_mut()
+                .define(name.clone(), value.clone());
+            library.math.scope_mut().define(name.clone(), value.clone());
+        }
+
+        Ok(Self {
+            source,
+            library: Arc::new(LazyHash::new(library)),
+            warnings,
+        })
+    }
+
+    /// Read and evaluate the preamble at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConvertError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| ConvertError::Io(format!("{}: {}", path.display(), err)))?;
+
+        Self::new(text)
+    }
+
+    pub fn text(&self) -> &str {
+        self.source.text()
+    }
+
+    /// Warnings emitted by the compiler while evaluating the preamble.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+}
+
+pub fn eval(content: &str) -> Result<EvaluatedMath, ConvertError> {
+    eval_with_preamble(content, None)
+}
+
+/// Evaluate `content` with the bindings of `preamble` in scope.
+pub fn eval_with_preamble(
+    content: &str,
+    preamble: Option<&Preamble>,
+) -> Result<EvaluatedMath, ConvertError> {
+    let mut document = eval_document(content, preamble)?;
+    let first = match document.equations.drain(..).next() {
+        Some(first) => first,
+        None => return Err(ConvertError::NoEquation),
     };
 
-    Ok((
-        typst_elem
-            .to_packed::<EquationElem>()
-            .expect("Conversion must be successful.")
-            .to_owned(),
-        styles,
-    ))
+    Ok(EvaluatedMath {
+        equation: first.equation,
+        styles: Some(first.styles),
+        warnings: document.warnings,
+        world: document.world,
+    })
+}
+
+/// Evaluate `content` and collect all of its equations in document order.
+pub fn eval_document(
+    content: &str,
+    preamble: Option<&Preamble>,
+) -> Result<EvaluatedDocument, ConvertError> {
+    let world = TypstWrapperWorld::with_preamble(content, preamble);
+    let (eval_res, warnings) = eval_world(&world).map_err(ConvertError::Compile)?;
+
+    let mut equations = Vec::new();
+    collect_equations(eval_res.content(), &Styles::new(), &mut equations);
+
+    Ok(EvaluatedDocument {
+        equations: equations
+            .into_iter()
+            .map(|(equation, styles)| EvaluatedEquation {
+                location: world.locate(equation.span()),
+                label: equation
+                    .label()
+                    .map(|label| label.repr().trim_matches(['<', '>']).to_string()),
+                equation,
+                styles,
+            })
+            .collect(),
+        warnings,
+        world,
+    })
 }
 
-fn eval_impl(world: &dyn World, traced: Tracked<Traced>) -> SourceResult<Module> {
+/// Collect equations in document order, together with the styles of the set rules
+/// that apply to them.
+fn collect_equations(
+    content: &Content,
+    outer: &Styles,
+    equations: &mut Vec<(Packed<EquationElem>, Styles)>,
+) {
+    if let Some(equation) = content.to_packed::<EquationElem>() {
+        equations.push((equation.to_owned(), outer.clone()));
+    } else if let Some(styled) = content.to_packed::<StyledElem>() {
+        let mut styles = styled.styles().to_owned();
+        styles.apply(outer.clone());
+        collect_equations(styled.child(), &styles, equations);
+    } else if let Some(sequence) = content.to_packed::<SequenceElem>() {
+        for child in sequence.children() {
+            collect_equations(child, outer, equations);
+        }
+    } else {
+        // Equations nested in other elements, e.g. `#strong[$x$]`, only see the
+        // set rules outside of that element.
+        for equation in content.query(&Selector::Elem(EquationElem::elem(), None)) {
+            let equation = equation
+                .to_packed::<EquationElem>()
+                .expect("Conversion must be successful.")
+                .to_owned();
+            equations.push((equation, outer.clone()));
+        }
+    }
+}
+
+/// Evaluate the main source of `world`, returning the module and the warnings.
+fn eval_world(world: &TypstWrapperWorld) -> Result<(Module, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let traced = Traced::default();
     let mut sink = Sink::new();
 
+    let module = eval_impl(world, traced.track(), &mut sink)
+        .map_err(|errors| to_diagnostics(world, &errors))?;
+
+    let delayed = sink.delayed();
+    if !delayed.is_empty() {
+        return Err(to_diagnostics(world, &delayed));
+    }
+
+    Ok((module, to_diagnostics(world, &sink.warnings())))
+}
+
+fn eval_impl(world: &dyn World, traced: Tracked<Traced>, sink: &mut Sink) -> SourceResult<Module> {
     let main = world.main();
     let main = world.source(main).unwrap();
 
@@ -66,41 +249,131 @@ fn eval_impl(world: &dyn World, traced: Tracked<Traced>) -> SourceResult<Module>
     )
 }
 
+/// Call a Typst function found in an evaluated equation, e.g. the `angle` of
+/// `cancel`, outside of layout and cast its result. Errors point to `span`.
+pub(crate) fn call_func<T: FromValue>(
+    world: &TypstWrapperWorld,
+    func: &Func,
+    args: impl IntoArgs,
+    style_chain: StyleChain,
+    span: Span,
+) -> Result<T, ConvertError> {
+    let introspector = Introspector::default();
+    let traced = Traced::default();
+    let mut sink = Sink::new();
+    let mut engine = Engine {
+        routines: &ROUTINES,
+        world: (world as &dyn World).track(),
+        introspector: introspector.track(),
+        traced: traced.track(),
+        sink: sink.track_mut(),
+        route: Route::default(),
+    };
+
+    func.call(
+        &mut engine,
+        Context::new(None, Some(style_chain)).track(),
+        args,
+    )
+    .and_then(|value| value.cast::<T>().at(span))
+    .map_err(|errors| ConvertError::Compile(to_diagnostics(world, &errors)))
+}
+
+fn to_diagnostics(world: &TypstWrapperWorld, diagnostics: &[SourceDiagnostic]) -> Vec<Diagnostic> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| Diagnostic::from_typst(diagnostic, |span| world.locate(span)))
+        .collect()
+}
+
+/// The standard library is the same for every input. Building it, and hashing it for
+/// `comemo`, is far more expensive than evaluating a typical equation, so it is shared
+/// by all worlds.
+static LIBRARY: LazyLock<LazyHash<Library>> = LazyLock::new(|| LazyHash::new(Library::default()));
+
+/// No fonts are loaded, the converter never lays out content.
+static BOOK: LazyLock<LazyHash<FontBook>> = LazyLock::new(|| LazyHash::new(FontBook::new()));
+
+/// Evict evaluation results memoized by `comemo`.
+///
+/// Evaluation of identical inputs is memoized, so converting the same or a largely
+/// shared source again is cheap. Long running services should call this regularly:
+/// every call ages the cache, and entries that were not used in the last `max_age`
+/// calls are dropped. `evict_cache(0)` clears the cache completely.
+pub fn evict_cache(max_age: usize) {
+    typst::comemo::evict(max_age);
+}
+
 /// Main interface that determines the environment for Typst.
+#[derive(Debug)]
 pub struct TypstWrapperWorld {
     /// The content of a source.
     source: Source,
 
-    /// The standard library.
-    library: LazyHash<Library>,
-
-    /// Metadata about all known fonts.
-    book: LazyHash<FontBook>,
-
-    /// Metadata about all known fonts.
-    fonts: Vec<Font>,
+    /// The preamble whose bindings are in scope of the main source.
+    preamble: Option<Preamble>,
 }
 
 impl TypstWrapperWorld {
     pub fn new(source: String) -> Self {
-        let fonts = Vec::with_capacity(0);
+        Self::with_main(Source::new(main_id(), source), None)
+    }
 
-        Self {
-            library: LazyHash::new(Library::default()),
-            book: LazyHash::new(FontBook::from_fonts(&fonts)),
-            fonts,
-            source: Source::detached(source),
-        }
+    /// A world whose main source sees the bindings of `preamble`.
+    pub fn with_preamble(source: &str, preamble: Option<&Preamble>) -> Self {
+        Self::with_main(
+            Source::new(main_id(), source.to_string()),
+            preamble.cloned(),
+        )
+    }
+
+    fn with_main(source: Source, preamble: Option<Preamble>) -> Self {
+        Self { source, preamble }
+    }
+
+    /// Resolve `span` to a location in the input or in the preamble.
+    pub(crate) fn locate(&self, span: Span) -> Option<Location> {
+        let id = span.id()?;
+        let source = World::source(self, id).ok()?;
+        let range = source.range(span)?;
+        let origin = if id == preamble_id() {
+            Origin::Preamble
+        } else {
+            Origin::Input
+        };
+
+        Location::resolve(&source, range, origin)
+    }
+
+    /// Whether `span` is a string literal, e.g. `"a"` in an equation.
+    pub(crate) fn is_string(&self, span: Span) -> bool {
+        let Some(source) = span.id().and_then(|id| World::source(self, id).ok()) else {
+            return false;
+        };
+        source
+            .find(span)
+            .is_some_and(|node| node.kind() == SyntaxKind::Str)
     }
 }
 
+fn main_id() -> FileId {
+    FileId::new(None, VirtualPath::new(MAIN_PATH))
+}
+
+fn preamble_id() -> FileId {
+    FileId::new(None, VirtualPath::new(PREAMBLE_PATH))
+}
+
 impl typst::World for TypstWrapperWorld {
     fn library(&self) -> &LazyHash<Library> {
-        &self.library
+        match &self.preamble {
+            Some(preamble) => &preamble.library,
+            None => &LIBRARY,
+        }
     }
 
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.book
+        &BOOK
     }
 
     fn main(&self) -> FileId {
@@ -110,6 +383,9 @@ impl typst::World for TypstWrapperWorld {
     fn source(&self, id: FileId) -> FileResult<Source> {
         if id == self.source.id() {
             Ok(self.source.clone())
+        } else if let Some(preamble) = self.preamble.as_ref().filter(|_| id == preamble_id()) {
+            // Spans of functions defined in the preamble point into it.
+            Ok(preamble.source.clone())
         } else {
             Err(FileError::Other(Some(EcoString::inline("sourceddd"))))
         }
@@ -119,8 +395,8 @@ impl typst::World for TypstWrapperWorld {
         Err(FileError::Other(Some(EcoString::inline("fileddd"))))
     }
 
-    fn font(&self, id: usize) -> Option<Font> {
-        self.fonts.get(id).cloned()
+    fn font(&self, _index: usize) -> Option<Font> {
+        None
     }
 
     /// In math equation, datatime may be useless.
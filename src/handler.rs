@@ -0,0 +1,124 @@
+//! Custom conversion of Typst elements.
+//!
+//! Handlers registered in [`ElementHandlers`] are consulted before the built-in
+//! conversion, e.g. to replace the CSS polyfill of `cancel` with a different one.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use typst::foundations::{Content, StyleChain};
+
+use crate::{
+    converter::ConvertOptions,
+    mathml::{self, Context},
+    node::{Element, Node},
+};
+
+/// Converts one kind of Typst element to MathML.
+///
+/// Closures can be registered with [`ElementHandlers::register_fn`].
+pub trait ElementHandler: Send + Sync {
+    /// Convert `content`, or return `None` to fall back to the built-in conversion.
+    fn convert(
+        &self,
+        content: &Content,
+        style_chain: StyleChain,
+        children: &ChildConverter,
+    ) -> Option<Node>;
+}
+
+/// A closure registered with [`ElementHandlers::register_fn`].
+struct FnHandler<F>(F);
+
+impl<F> ElementHandler for FnHandler<F>
+where
+    F: Fn(&Content, StyleChain, &ChildConverter) -> Option<Node> + Send + Sync,
+{
+    fn convert(
+        &self,
+        content: &Content,
+        style_chain: StyleChain,
+        children: &ChildConverter,
+    ) -> Option<Node> {
+        (self.0)(content, style_chain, children)
+    }
+}
+
+/// Handlers keyed by element name, e.g. `cancel` or `frac`.
+///
+/// ```no_run
+/// use convert_typst_math_to_mathml::converter::{ConvertOptions, Converter};
+/// use convert_typst_math_to_mathml::node::Element;
+/// use typst::math::CancelElem;
+///
+/// let mut options = ConvertOptions::default();
+/// options.handlers.register_fn("cancel", |content, styles, children| {
+///     let cancel = content.to_packed::<CancelElem>()?;
+///     let body = children.convert_required(cancel.body(), styles);
+///     Some(Element::new("mrow").with_attr("class", "strike").with_child(body).into())
+/// });
+/// let converter = Converter::new(options);
+/// ```
+#[derive(Clone, Default)]
+pub struct ElementHandlers {
+    handlers: HashMap<String, Arc<dyn ElementHandler>>,
+}
+
+impl ElementHandlers {
+    /// Register `handler` for elements named `name`, replacing a previous handler.
+    pub fn register(&mut self, name: impl Into<String>, handler: impl ElementHandler + 'static) {
+        self.handlers.insert(name.into(), Arc::new(handler));
+    }
+
+    /// Register a closure with the signature of [`ElementHandler::convert`].
+    pub fn register_fn<F>(&mut self, name: impl Into<String>, handler: F)
+    where
+        F: Fn(&Content, StyleChain, &ChildConverter) -> Option<Node> + Send + Sync + 'static,
+    {
+        self.register(name, FnHandler(handler));
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.handlers.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn ElementHandler> {
+        self.handlers.get(name).map(|handler| handler.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl Debug for ElementHandlers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.handlers.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+/// Converts the children of an element handled by an [`ElementHandler`], with the
+/// same options and handlers as the element itself.
+pub struct ChildConverter<'a> {
+    pub(crate) ctx: &'a Context<'a>,
+}
+
+impl ChildConverter<'_> {
+    pub fn options(&self) -> &ConvertOptions {
+        self.ctx.options
+    }
+
+    /// Convert `content`, `None` if it produces no MathML at all, e.g. for spaces.
+    pub fn convert(&self, content: &Content, style_chain: StyleChain) -> Option<Node> {
+        mathml::convert_to_mathml_impl(self.ctx, content, style_chain)
+    }
+
+    /// Convert `content`, using an empty `<mrow>` if it produces nothing.
+    pub fn convert_required(&self, content: &Content, style_chain: StyleChain) -> Node {
+        self.convert(content, style_chain)
+            .unwrap_or_else(|| Element::new("mrow").into())
+    }
+}
@@ -1,3 +1,10 @@
+pub mod converter;
+pub mod css;
+pub mod diagnostic;
 pub mod eval_math;
+pub mod handler;
 pub mod mathml;
+pub mod node;
+mod number;
+mod operator;
 mod style;
@@ -1,121 +1,306 @@
-use std::slice::Iter;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 
 use typst::{
-    foundations::{Chainable, Content, SequenceElem, Smart, StyleChain, StyledElem, Styles},
-    layout::{FixedAlignment, HElem, HideElem, Spacing},
+    diag::SourceDiagnostic,
+    foundations::{
+        Chainable, Content, Packed, Resolve, SequenceElem, Smart, StyleChain, StyledElem, Styles,
+    },
+    layout::{Abs, Angle, BoxElem, FixedAlignment, HElem, HideElem, Ratio, Rel, Spacing},
     math::{
-        AccentElem, AttachElem, BinomElem, CancelElem, CasesElem, ClassElem, EquationElem,
-        FracElem, LimitsElem, LrElem, MatElem, MidElem, OpElem, OverbraceElem, OverbracketElem,
-        OverlineElem, OverparenElem, OvershellElem, PrimesElem, RootElem, ScriptsElem, StretchElem,
-        UnderbraceElem, UnderbracketElem, UnderlineElem, UnderparenElem, UndershellElem, VecElem,
+        AccentElem, AlignPointElem, AttachElem, Augment, AugmentOffsets, BinomElem, CancelAngle,
+        CancelElem, CasesElem, ClassElem, EquationElem, FracElem, LimitsElem, LrElem, MatElem,
+        MathSize, MidElem, OpElem, OverbraceElem, OverbracketElem, OverlineElem, OverparenElem,
+        OvershellElem, PrimesElem, RootElem, ScriptsElem, StretchElem, UnderbraceElem,
+        UnderbracketElem, UnderlineElem, UnderparenElem, UndershellElem, VecElem,
     },
-    text::TextElem,
+    model::{EmphElem, StrongElem},
+    syntax::Span,
+    text::{LinebreakElem, SmallcapsElem, SpaceElem, TextElem},
+    visualize::{DashLength, Paint, Stroke},
 };
 use unicode_math_class::MathClass;
 
 use crate::{
-    eval_math::eval,
-    style::{self, character_variant},
+    converter::{
+        ConvertOptions, Converter, DisplayMode, SpacingMode, TargetProfile, UnknownElementPolicy,
+    },
+    diagnostic::{ConvertError, Diagnostic},
+    eval_math::{call_func, TypstWrapperWorld},
+    handler::ChildConverter,
+    node::{walk_element_mut, Element, Node, VisitorMut},
+    number,
+    operator::Limits,
+    style::{self, character_variant, length},
 };
 
+/// MathML produced from a successful conversion.
+#[derive(Debug, Clone)]
+pub struct Converted {
+    pub mathml: String,
+    /// Warnings emitted by the Typst compiler, the conversion still succeeded.
+    pub warnings: Vec<Diagnostic>,
+    /// How often each element without MathML counterpart was encountered.
+    pub unsupported: BTreeMap<String, usize>,
+}
+
+/// Convert the first equation in `content` to MathML.
+///
+/// # Panics
+///
+/// Panics if the input fails to compile or contains no equation, use
+/// [`try_convert_to_mathml`] to handle these cases.
 pub fn convert_to_mathml(content: &str, add_annotation: bool) -> String {
-    let (equation, styles) = eval(&content).unwrap();
-    // println!("{:#?}", equation);
-    let styles = styles.unwrap_or(Styles::new());
-    let style_chain = StyleChain::new(&styles);
-
-    let is_block = equation.block(style_chain);
-    let attrs = if is_block { r#" display="block""# } else { "" };
-    let annotation = if add_annotation {
-        format!(
-            r#"<annotation encoding="application/x-typst">{}</annotation>"#,
-            content
-        )
-    } else {
-        String::with_capacity(0)
+    match try_convert_to_mathml(content, add_annotation) {
+        Ok(converted) => converted.mathml,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Convert the first equation in `content` to MathML, reporting compiler errors
+/// instead of panicking.
+pub fn try_convert_to_mathml(
+    content: &str,
+    add_annotation: bool,
+) -> Result<Converted, ConvertError> {
+    Converter::new(ConvertOptions {
+        annotation: add_annotation,
+        ..Default::default()
+    })
+    .convert(content)
+}
+
+/// State shared by all `process_*` functions during one conversion.
+pub(crate) struct Context<'a> {
+    pub options: &'a ConvertOptions,
+    /// Whether the equation is displayed as a block.
+    pub block: bool,
+    /// The world the equation was evaluated in, to resolve spans and call functions.
+    pub world: &'a TypstWrapperWorld,
+    /// Names of the elements without MathML counterpart and how often they occur.
+    pub unsupported: RefCell<BTreeMap<String, usize>>,
+    /// The first error, the conversion fails once it is done.
+    pub error: RefCell<Option<ConvertError>>,
+    /// The size of the content being converted, scripts are smaller.
+    pub size: Cell<MathSize>,
+}
+
+impl Context<'_> {
+    /// Fail the conversion with `err`, unless it already failed.
+    fn fail(&self, err: ConvertError) {
+        self.error.borrow_mut().get_or_insert(err);
+    }
+
+    /// Count `name` as a feature without MathML counterpart.
+    fn report_unsupported(&self, name: &str) {
+        *self
+            .unsupported
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default() += 1;
+    }
+
+    /// The size of scripts attached to content of the current size.
+    fn script_size(&self) -> MathSize {
+        match self.size.get() {
+            MathSize::Display | MathSize::Text => MathSize::Script,
+            MathSize::Script | MathSize::ScriptScript => MathSize::ScriptScript,
+        }
+    }
+
+    /// Run `f` to convert content at a different size, e.g. scripts.
+    fn at_size<T>(&self, size: MathSize, f: impl FnOnce() -> T) -> T {
+        let outer = self.size.replace(size);
+        let res = f();
+        self.size.set(outer);
+        res
+    }
+}
+
+/// Convert `equation` to a `<math>` element, adding the elements without MathML
+/// counterpart to `unsupported`.
+pub(crate) fn convert_equation(
+    options: &ConvertOptions,
+    source: &str,
+    equation: &Packed<EquationElem>,
+    styles: Option<&Styles>,
+    world: &TypstWrapperWorld,
+    unsupported: &mut BTreeMap<String, usize>,
+) -> Result<Element, ConvertError> {
+    let empty = Styles::new();
+    let style_chain = StyleChain::new(styles.unwrap_or(&empty));
+    let ctx = Context {
+        options,
+        block: is_block(options, equation, style_chain),
+        world,
+        unsupported: RefCell::default(),
+        error: RefCell::default(),
+        size: Cell::new(MathSize::Text),
     };
+    if ctx.block {
+        ctx.size.set(MathSize::Display);
+    }
+
+    let mut math = Element::new("math");
+    if options.xmlns {
+        math.set_attr("xmlns", "http://www.w3.org/1998/Math/MathML");
+    }
+    if ctx.block {
+        math.set_attr("display", "block");
+    }
+
+    let mut semantics =
+        Element::new("semantics").with_child(convert_required(&ctx, equation.body(), style_chain));
+    if options.annotation {
+        semantics = semantics.with_child(
+            Element::new("annotation")
+                .with_attr("encoding", "application/x-typst")
+                .with_text(source),
+        );
+    }
+
+    for (name, count) in ctx.unsupported.into_inner() {
+        *unsupported.entry(name).or_default() += count;
+    }
+    match ctx.error.into_inner() {
+        Some(err) => Err(err),
+        None => Ok(math.with_child(semantics)),
+    }
+}
 
-    format!(
-        // r#"<math xmlns="http://www.w3.org/1998/Math/MathML"{}><semantics>{}{}</semantics></math>"#,
-        r#"<math{}><semantics>{}{}</semantics></math>"#,
-        attrs,
-        convert_to_mathml_impl(equation.body(), style_chain),
-        annotation
-    )
+/// Whether the equation is displayed as a block, after applying the display override.
+pub(crate) fn is_block(
+    options: &ConvertOptions,
+    equation: &Packed<EquationElem>,
+    style_chain: StyleChain,
+) -> bool {
+    match options.display {
+        DisplayMode::Auto => equation.block(style_chain),
+        DisplayMode::Inline => false,
+        DisplayMode::Block => true,
+    }
 }
 
-fn convert_to_mathml_impl(content: &Content, style_chain: StyleChain) -> String {
+/// Convert `content`, `None` if it produces no MathML at all, e.g. for spaces.
+pub(crate) fn convert_to_mathml_impl(
+    ctx: &Context,
+    content: &Content,
+    style_chain: StyleChain,
+) -> Option<Node> {
     let elem_type = content.elem().name();
-    match elem_type {
+    if let Some(handler) = ctx.options.handlers.get(elem_type) {
+        // The built-in conversion converts the children again after a fallback, roll
+        // back what the handler reported for them so that it is not counted twice.
+        let unsupported = ctx.unsupported.borrow().clone();
+        let failed = ctx.error.borrow().is_some();
+        let children = ChildConverter { ctx };
+        if let Some(node) = handler.convert(content, style_chain, &children) {
+            return Some(node);
+        }
+        *ctx.unsupported.borrow_mut() = unsupported;
+        if !failed {
+            *ctx.error.borrow_mut() = None;
+        }
+    }
+
+    let node = match elem_type {
         "frac" => {
             let coerced = content
                 .to_packed::<FracElem>()
                 .expect("Type conversion to `FracElem` must be successful.");
-            let numerator: String = convert_to_mathml_impl(coerced.num(), style_chain);
-            let denomenator: String = convert_to_mathml_impl(coerced.denom(), style_chain);
-            format!("<mfrac>{}{}</mfrac>", numerator, denomenator)
-        }
-        "accent" => {
-            let coerced = content
-                .to_packed::<AccentElem>()
-                .expect("Type conversion to `AccentElem` must be successful.");
-            format!(
-                r#"<mover accent="true">{}<mo>{}</mo></mover>"#,
-                convert_to_mathml_impl(coerced.base(), style_chain),
-                coerced.accent().0,
-            )
+            let size = match ctx.size.get() {
+                MathSize::Display => MathSize::Text,
+                MathSize::Text => MathSize::Script,
+                MathSize::Script | MathSize::ScriptScript => MathSize::ScriptScript,
+            };
+            let numerator = ctx.at_size(size, || convert_required(ctx, coerced.num(), style_chain));
+            let denomenator =
+                ctx.at_size(size, || convert_required(ctx, coerced.denom(), style_chain));
+            Element::new("mfrac")
+                .with_child(numerator)
+                .with_child(denomenator)
+                .into()
         }
+        "accent" => process_accent(ctx, content, style_chain),
         "limits" => {
             let coerced = content
                 .to_packed::<LimitsElem>()
                 .expect("Type conversion to `LimitsElem` must be successful.");
-            convert_to_mathml_impl(coerced.body(), style_chain)
+            return convert_to_mathml_impl(ctx, coerced.body(), style_chain);
         }
         "scripts" => {
             let coerced = content
                 .to_packed::<ScriptsElem>()
                 .expect("Type conversion to `ScriptsElem` must be successful.");
-            format!(
-                "<mrow>{}</mrow>",
-                convert_to_mathml_impl(coerced.body(), style_chain)
-            )
+            Element::new("mrow")
+                .with_children(convert_to_mathml_impl(ctx, coerced.body(), style_chain))
+                .into()
         }
-        "sequence" => process_sequence(content, style_chain),
-        "lr" => process_lr(content, style_chain),
-        "attach" => process_attach(content, style_chain),
-        "text" => process_text(content, style_chain),
-        "root" => process_root(content, style_chain),
-        "binom" => process_binom(content, style_chain),
-        "cancel" => process_cancel(content, style_chain),
-        "op" => process_op(content, style_chain),
-        "cases" => process_cases(content, style_chain),
-        "mat" => process_mat(content, style_chain),
-        "vec" => process_vec(content, style_chain),
-        "class" => process_class(content, style_chain),
-        "equation" => process_equation(content, style_chain),
+        "sequence" => process_sequence(ctx, content, style_chain),
+        "lr" => process_lr(ctx, content, style_chain),
+        "attach" => process_attach(ctx, content, style_chain),
+        "text" => return process_text(ctx, content, style_chain),
+        "root" => process_root(ctx, content, style_chain),
+        "binom" => process_binom(ctx, content, style_chain),
+        "cancel" => process_cancel(ctx, content, style_chain),
+        "op" => process_op(ctx, content, style_chain),
+        "cases" => process_cases(ctx, content, style_chain),
+        "mat" => process_mat(ctx, content, style_chain),
+        "vec" => process_vec(ctx, content, style_chain),
+        "class" => process_class(ctx, content, style_chain),
+        "equation" => return process_equation(ctx, content, style_chain),
         "primes" => process_primes(content),
-        "styled" => process_styled(content, style_chain),
-        "h" => process_h(content),
-        "hide" => process_hide(content, style_chain),
-        "stretch" => process_stretch(content, style_chain),
-        "mid" => process_mid(content, style_chain),
-        "underline" => process_underline(content, style_chain),
-        "overline" => process_overline(content, style_chain),
-        "underbrace" => process_underbrace(content, style_chain),
-        "overbrace" => process_overbrace(content, style_chain),
-        "underbracket" => process_underbracket(content, style_chain),
-        "overbracket" => process_overbracket(content, style_chain),
-        "underparen" => process_underparen(content, style_chain),
-        "overparen" => process_overparen(content, style_chain),
-        "undershell" => process_undershell(content, style_chain),
-        "overshell" => process_overshell(content, style_chain),
-        // FIXME: align-point should be processed in `Sequence`.
-        "space" | "align-point" => "".to_string(),
-        _ => format!("<merror>`{}` Not Implemented Yet</merror>", elem_type),
-    }
-}
-
-fn process_attach(content: &Content, style_chain: StyleChain) -> String {
+        "styled" => return process_styled(ctx, content, style_chain),
+        "h" => return process_h(ctx, content, style_chain),
+        "strong" | "emph" | "smallcaps" => return process_markup(ctx, content, style_chain),
+        "raw" => process_raw(ctx, content),
+        "hide" => process_hide(ctx, content, style_chain),
+        "stretch" => return process_stretch(ctx, content, style_chain),
+        "mid" => process_mid(ctx, content, style_chain),
+        "underline" => process_underline(ctx, content, style_chain),
+        "overline" => process_overline(ctx, content, style_chain),
+        "underbrace" | "overbrace" | "underbracket" | "overbracket" | "underparen"
+        | "overparen" | "undershell" | "overshell" => process_spreader(ctx, content, style_chain),
+        // Alignment points and linebreaks split the rows they are in, see
+        // `collect_aligned`, and spaces are kept by `finish_row` where Typst renders
+        // them.
+        "space" | "align-point" | "linebreak" => return None,
+        _ => return process_unknown(ctx, content),
+    };
+
+    Some(node)
+}
+
+/// Convert `content` where MathML requires exactly one element, e.g. an argument
+/// of `<mfrac>`, using an empty `<mrow>` if `content` produces nothing.
+fn convert_required(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
+    convert_to_mathml_impl(ctx, content, style_chain).unwrap_or_else(|| Element::new("mrow").into())
+}
+
+/// Apply [`ConvertOptions::unknown_element`] to an element without MathML counterpart.
+fn process_unknown(ctx: &Context, content: &Content) -> Option<Node> {
+    let name = content.elem().name();
+    ctx.report_unsupported(name);
+
+    match &ctx.options.unknown_element {
+        UnknownElementPolicy::Merror => Some(
+            Element::new("merror")
+                .with_text(format!("`{}` Not Implemented Yet", name))
+                .into(),
+        ),
+        UnknownElementPolicy::Error => {
+            ctx.fail(ConvertError::UnsupportedElement {
+                name: name.to_string(),
+                location: ctx.world.locate(content.span()),
+            });
+            None
+        }
+        UnknownElementPolicy::Skip => None,
+        UnknownElementPolicy::Callback(callback) => callback.call(content),
+    }
+}
+
+fn process_attach(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<AttachElem>()
         .expect("Type conversion to `AttachElem` must be successful.");
@@ -130,18 +315,22 @@ fn process_attach(content: &Content, style_chain: StyleChain) -> String {
     let mut top_right = elem.tr(style_chain);
     let mut bottom_right = elem.br(style_chain);
 
-    let base_type = base.elem().name();
-    let base_str = convert_to_mathml_impl(base, style_chain);
-    let is_limits = base_type == "limits"
-        || base_str.contains("∑")
-        || (base_type == "op"
-            && base
-                .to_packed::<OpElem>()
-                .expect("Type conversion to `OpElem` must be successful.")
-                .limits(style_chain));
-    let is_stretch = base_type == "stretch";
-
-    if !is_limits && !is_stretch {
+    let mut base_node = if base.is::<StretchElem>() {
+        stretch(ctx, base, style_chain, true).unwrap_or_else(|| Element::new("mrow").into())
+    } else {
+        convert_required(ctx, base, style_chain)
+    };
+    let limits = Limits::for_content(base, style_chain);
+    let is_limits = limits.active(content_size(ctx, base) == MathSize::Display);
+
+    if !is_limits {
+        // Primes are part of the superscript, before an explicit one, e.g. `f'^2`.
+        if let (Some(t), Some(tr)) = (&top, &top_right) {
+            if tr.is::<PrimesElem>() {
+                top_right = Some(tr.clone() + t.clone());
+                top = None;
+            }
+        }
         if top.is_some() && top_right.is_none() {
             [top, top_right] = [top_right, top];
         }
@@ -150,85 +339,86 @@ fn process_attach(content: &Content, style_chain: StyleChain) -> String {
         }
     }
 
-    match (top, bottom, top_left, bottom_left, top_right, bottom_right) {
-        (None, None, Some(tl), Some(bl), Some(tr), Some(br)) => {
-            format!(
-                "<mmultiscripts>{}{}{}<mprescripts />{}{}</mmultiscripts>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&br, style_chain),
-                convert_to_mathml_impl(&tr, style_chain),
-                convert_to_mathml_impl(&bl, style_chain),
-                convert_to_mathml_impl(&tl, style_chain),
-            )
-        }
-        (Some(t), Some(b), None, None, None, None) => {
-            format!(
-                "<munderover>{}{}{}</munderover>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&b, style_chain),
-                convert_to_mathml_impl(&t, style_chain)
-            )
+    // Renderers move limits of operators with `movablelimits` to scripts when they
+    // are not in display style, e.g. in a fraction, like Typst does.
+    if let Some(mo) = base_node
+        .as_element_mut()
+        .filter(|element| element.name == "mo")
+    {
+        // The base may be in display size on its own, e.g. `display(sum)`, renderers
+        // must keep its limits then.
+        match limits {
+            Limits::Display if is_limits && ctx.size.get() == MathSize::Display => {
+                mo.set_attr("movablelimits", "true")
+            }
+            Limits::Always | Limits::Display if is_limits => mo.set_attr("movablelimits", "false"),
+            _ => {}
         }
-        (Some(t), None, None, None, None, None) => {
-            format!(
-                "<mover>{}{}</mover>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&t, style_chain)
-            )
+    }
+
+    let script = |attach: &Content| {
+        let node = ctx.at_size(ctx.script_size(), || {
+            convert_required(ctx, attach, style_chain)
+        });
+        // A script may be a single operator, e.g. `x^+`.
+        let mut row = vec![(attach, node)];
+        resolve_forms(&mut row);
+        let mut row: Vec<_> = row.into_iter().map(|(_, node)| node).collect();
+        if ctx.options.spacing == SpacingMode::Typst {
+            apply_spacing(ctx, &mut row);
         }
-        (None, Some(b), None, None, None, None) => {
-            format!(
-                "<munder>{}{}</munder>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&b, style_chain)
-            )
+        row.remove(0)
+    };
+    let (name, children) = match (top, bottom, top_left, bottom_left, top_right, bottom_right) {
+        (None, None, Some(tl), Some(bl), Some(tr), Some(br)) => (
+            "mmultiscripts",
+            vec![
+                base_node,
+                script(&br),
+                script(&tr),
+                Element::new("mprescripts").into(),
+                script(&bl),
+                script(&tl),
+            ],
+        ),
+        (Some(t), Some(b), None, None, None, None) => {
+            ("munderover", vec![base_node, script(&b), script(&t)])
         }
+        (Some(t), None, None, None, None, None) => ("mover", vec![base_node, script(&t)]),
+        (None, Some(b), None, None, None, None) => ("munder", vec![base_node, script(&b)]),
         (None, None, None, None, Some(tr), Some(br)) => {
-            format!(
-                "<msubsup>{}{}{}</msubsup>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&br, style_chain),
-                convert_to_mathml_impl(&tr, style_chain),
-            )
-        }
-        (None, None, None, None, Some(tr), None) => {
-            format!(
-                "<msup>{}{}</msup>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&tr, style_chain),
-            )
-        }
-        (None, None, None, None, None, Some(br)) => {
-            format!(
-                "<msub>{}{}</msub>",
-                convert_to_mathml_impl(base, style_chain),
-                convert_to_mathml_impl(&br, style_chain),
-            )
+            ("msubsup", vec![base_node, script(&br), script(&tr)])
         }
-        (None, None, None, None, None, None) => convert_to_mathml_impl(base, style_chain),
+        (None, None, None, None, Some(tr), None) => ("msup", vec![base_node, script(&tr)]),
+        (None, None, None, None, None, Some(br)) => ("msub", vec![base_node, script(&br)]),
+        (None, None, None, None, None, None) => return base_node,
         (t, b, tl, bl, tr, br) => {
-            let row_or_attach = |attach| {
+            let row_or_attach = |attach: Option<Content>| {
                 if let Some(a) = attach {
-                    convert_to_mathml_impl(&a, style_chain)
+                    script(&a)
                 } else {
-                    "<mrow></mrow>".to_string()
+                    Element::new("mrow").into()
                 }
             };
-            format!(
-                    "<munderover><mmultiscripts>{}{}{}<mprescripts />{}{}</mmultiscripts>{}{}</munderover>",
-                        convert_to_mathml_impl(base, style_chain),
-                        row_or_attach(br),
-                        row_or_attach(tr),
-                        row_or_attach(bl),
-                        row_or_attach(tl),
-                        row_or_attach(b),
-                        row_or_attach(t),
-                    )
+            let multiscripts = Element::new("mmultiscripts").with_children([
+                base_node,
+                row_or_attach(br),
+                row_or_attach(tr),
+                Element::new("mprescripts").into(),
+                row_or_attach(bl),
+                row_or_attach(tl),
+            ]);
+            (
+                "munderover",
+                vec![multiscripts.into(), row_or_attach(b), row_or_attach(t)],
+            )
         }
-    }
+    };
+
+    Element::new(name).with_children(children).into()
 }
 
-fn process_text(content: &Content, style_chain: StyleChain) -> String {
+fn process_text(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
     let coerced = content
         .to_packed::<TextElem>()
         .expect("Type conversion to `TextElem` must be successful.");
@@ -246,48 +436,115 @@ fn process_text(content: &Content, style_chain: StyleChain) -> String {
         })
         .collect::<String>();
 
-    let identifier_attr = if matches!(is_italic, Smart::Custom(false)) {
-        r#" mathvariant="normal""#
-    } else {
-        ""
-    };
+    let is_normal = matches!(is_italic, Smart::Custom(false));
 
-    if original_text.parse::<f64>().is_ok()
-        || original_text.parse::<i64>().is_ok()
-        || original_text == "∞"
-    {
-        return format!("<mn>{}</mn>", original_text);
+    // Strings are text whatever their length, e.g. `"a"` or `"1"`.
+    let is_string = ctx.world.is_string(content.span());
+    if !is_string && (number::is_number(&original_text) || original_text == "∞") {
+        return Some(Element::new("mn").with_text(original_text).into());
     }
 
     let mut chars = text.chars();
-    let first_char = match chars.next() {
-        Some(it) => it,
-        None => return String::with_capacity(0),
-    };
-    if chars.next().is_none() {
+    let first_char = chars.next()?;
+    if chars.next().is_none() && !is_string {
         let char_class = match unicode_math_class::class(first_char) {
             Some(it) => it,
-            None => return format!("<mi{}>{}</mi>", identifier_attr, first_char),
+            None => return Some(identifier(first_char.to_string(), is_normal)),
         };
 
-        math_class_helper(
-            first_char.to_string().as_str(),
-            Some(&original_text),
+        Some(math_class_helper(
+            Node::Text(first_char.to_string()),
+            &original_text,
             &char_class,
             style_chain,
+        ))
+    } else {
+        // Strings and other text of several characters, e.g. `"area"`, are text,
+        // identifiers are single characters or `op`. The spaces around it are kept by
+        // `finish_row`.
+        Some(
+            Element::new("mtext")
+                .with_text(keep_text_spaces(&text))
+                .into(),
         )
+    }
+}
+
+/// Renderers trim and collapse whitespace in token elements, so spaces at the
+/// start and end of `text` and repeated spaces become no-break spaces.
+fn keep_text_spaces(text: &str) -> String {
+    let trimmed = text.trim_end_matches(' ');
+    let mut res = String::with_capacity(text.len());
+    let mut last = None;
+    for (idx, c) in text.char_indices() {
+        let keep = c == ' ' && (matches!(last, None | Some(' ')) || idx >= trimmed.len());
+        res.push(if keep { '\u{A0}' } else { c });
+        last = Some(c);
+    }
+    res
+}
+
+/// Markup in an equation, e.g. `#strong[..]`, as `mathvariant` for full MathML
+/// and as CSS for MathML Core, which only supports `mathvariant="normal"`.
+fn process_markup(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
+    let (body, variant, declaration) = if let Some(strong) = content.to_packed::<StrongElem>() {
+        (strong.body(), Some("bold"), "font-weight: bold")
+    } else if let Some(emph) = content.to_packed::<EmphElem>() {
+        (emph.body(), Some("italic"), "font-style: italic")
     } else {
-        let text = escape_helper(&text);
-        if text.find(" ").is_some() {
-            // FIXME: it should add begging and endding space according to pre and post elements.
-            format!("<mtext>&nbsp;{}&nbsp;</mtext>", text)
-        } else {
-            format!("<mi{}>{}</mi>", identifier_attr, text)
+        let smallcaps = content
+            .to_packed::<SmallcapsElem>()
+            .expect("Type conversion to `SmallcapsElem` must be successful.");
+        // There is no small caps variant in MathML.
+        (smallcaps.body(), None, "font-variant: small-caps")
+    };
+
+    let mut node = convert_to_mathml_impl(ctx, body, style_chain)?;
+    match variant {
+        Some(variant) if ctx.options.profile == TargetProfile::Full => {
+            node.accept_mut(&mut SetVariant(variant));
+        }
+        _ => match node.as_element_mut() {
+            Some(element) => push_style(element, declaration),
+            None => {
+                node = Element::new("mrow")
+                    .with_attr("style", declaration)
+                    .with_child(node)
+                    .into()
+            }
+        },
+    }
+    Some(node)
+}
+
+/// Sets `mathvariant` on all token elements, combining bold and italic.
+struct SetVariant(&'static str);
+
+impl VisitorMut for SetVariant {
+    fn visit_element_mut(&mut self, element: &mut Element) {
+        if matches!(element.name.as_str(), "mi" | "mn" | "mo" | "mtext") {
+            let variant = match (element.attr("mathvariant"), self.0) {
+                (Some("bold"), "italic") | (Some("italic"), "bold") => "bold-italic",
+                (Some("bold-italic"), _) => "bold-italic",
+                (_, variant) => variant,
+            };
+            element.set_attr("mathvariant", variant);
         }
+        walk_element_mut(self, element);
+    }
+}
+
+/// Raw text in an equation, e.g. `` #raw("x + y") ``, as monospace text.
+fn process_raw(ctx: &Context, content: &Content) -> Node {
+    let text = Element::new("mtext").with_text(keep_text_spaces(&content.plain_text()));
+    match ctx.options.profile {
+        TargetProfile::Core => text.with_attr("style", "font-family: monospace"),
+        TargetProfile::Full => text.with_attr("mathvariant", "monospace"),
     }
+    .into()
 }
 
-fn process_root(content: &Content, style_chain: StyleChain) -> String {
+fn process_root(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<RootElem>()
         .expect("Type conversion to `RootElem` must be successful.");
@@ -295,110 +552,355 @@ fn process_root(content: &Content, style_chain: StyleChain) -> String {
     let radicand = coerced.radicand();
 
     if let Some(index) = index {
-        format!(
-            "<mroot>{}{}</mroot>",
-            convert_to_mathml_impl(radicand, style_chain),
-            convert_to_mathml_impl(&index, style_chain)
-        )
+        Element::new("mroot")
+            .with_child(convert_required(ctx, radicand, style_chain))
+            .with_child(ctx.at_size(MathSize::ScriptScript, || {
+                convert_required(ctx, &index, style_chain)
+            }))
+            .into()
     } else {
-        format!(
-            "<msqrt>{}</msqrt>",
-            convert_to_mathml_impl(radicand, style_chain)
-        )
+        Element::new("msqrt")
+            .with_children(convert_to_mathml_impl(ctx, radicand, style_chain))
+            .into()
     }
 }
 
 /// MDN Reference: https://developer.mozilla.org/en-US/docs/Web/MathML/Element/mfrac#fraction_without_bar
-fn process_binom(content: &Content, style_chain: StyleChain) -> String {
+fn process_binom(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<BinomElem>()
         .expect("Type conversion to `BinomElem` must be successful.");
 
-    format!(
-        r#"<mrow><mo>(</mo><mfrac linethickness="0">{}{}</mfrac><mo>)</mo></mrow>"#,
-        convert_to_mathml_impl(coerced.upper(), style_chain),
-        format!(
-            "<mrow>{}</mrow>",
-            coerced
-                .lower()
-                .iter()
-                .map(|child| convert_to_mathml_impl(child, style_chain))
-                .collect::<Vec<_>>()
-                .join("<mo>,</mo>")
-        ),
-    )
+    let mut lower = Element::new("mrow");
+    for (idx, child) in coerced.lower().iter().enumerate() {
+        if idx > 0 {
+            lower
+                .children
+                .push(Element::new("mo").with_text(",").into());
+        }
+        lower
+            .children
+            .extend(convert_to_mathml_impl(ctx, child, style_chain));
+    }
+
+    Element::new("mrow")
+        .with_child(Element::new("mo").with_text("("))
+        .with_child(
+            Element::new("mfrac")
+                .with_attr("linethickness", "0")
+                .with_child(convert_required(ctx, coerced.upper(), style_chain))
+                .with_child(lower),
+        )
+        .with_child(Element::new("mo").with_text(")"))
+        .into()
 }
 
-fn process_cancel(content: &Content, style_chain: StyleChain) -> String {
+fn process_cancel(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<CancelElem>()
         .expect("Type conversion to `CancelElem` must be successful.");
 
-    // `menclose` is not supported in MathML, but FireFox support it.
-    // For other browses, a polyfill is necessary:
-    //
-    // <style>
-    // .equation-typst-cancel {
-    //   position: relative;
-    //   padding: 0.5ex 0ex;
-    // }
-
-    // .equation-typst-cancel-wrapper-placeholder {
-    //   display: inline-block;
-    //   position: absolute;
-    //   left: 0.5px;
-    //   bottom: 0;
-    //   width: 100%;
-    //   height: 100%;
-    //   background-color: black;
-    //   clip-path: polygon(0.05em 100%, 0em calc(100% - 0.05em), calc(100% - 0.05em) 0em, 100% 0.05em);
-    // }
-    // </style>
-    format!(
-        r#"<menclose class="equation-typst-cancel" notation="updiagonalstrike">{}<mrow class="equation-typst-cancel-wrapper-placeholder"></mrow></menclose>"#,
-        convert_to_mathml_impl(coerced.body(), style_chain)
-    )
-}
-
-fn process_op(content: &Content, style_chain: StyleChain) -> String {
+    let body = convert_to_mathml_impl(ctx, coerced.body(), style_chain);
+    let inverted = coerced.inverted(style_chain);
+    let cross = coerced.cross(style_chain);
+    let line_length = coerced.length(style_chain);
+    let stroke = coerced.stroke(style_chain);
+
+    // Typst measures the angle from the vertical and passes the angle of the
+    // diagonal to angle functions. Without layout, the body is assumed to be square.
+    let angle = match coerced.angle(style_chain) {
+        Smart::Auto => None,
+        Smart::Custom(CancelAngle::Angle(angle)) => Some(angle.to_deg()),
+        Smart::Custom(CancelAngle::Func(func)) => {
+            match call_func::<Angle>(
+                ctx.world,
+                &func,
+                [Angle::deg(45.0)],
+                style_chain,
+                content.span(),
+            ) {
+                Ok(angle) => Some(angle.to_deg()),
+                Err(err) => {
+                    ctx.fail(err);
+                    None
+                }
+            }
+        }
+    };
+
+    // Each line is mirrored or not, `inverted` mirrors the only line and `cross`
+    // draws both.
+    let lines = if cross {
+        vec![false, true]
+    } else {
+        vec![inverted]
+    };
+    let notation = lines
+        .iter()
+        .map(|&mirrored| match angle {
+            Some(angle) => {
+                let angle = if mirrored { -angle } else { angle };
+                let angle = angle.rem_euclid(180.0);
+                if angle == 0.0 {
+                    "verticalstrike"
+                } else if angle == 90.0 {
+                    "horizontalstrike"
+                } else if angle < 90.0 {
+                    "updiagonalstrike"
+                } else {
+                    "downdiagonalstrike"
+                }
+            }
+            None if mirrored => "downdiagonalstrike",
+            None => "updiagonalstrike",
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let thickness = match stroke.thickness {
+        Smart::Custom(thickness) if thickness != Abs::pt(0.5) => Some(thickness),
+        _ => None,
+    };
+    let style = line_style(&stroke);
+
+    let menclose = Element::new("menclose").with_attr("notation", notation);
+    if ctx.options.profile == TargetProfile::Full {
+        // MathML has no counterpart to the thickness and dash of the lines.
+        if thickness.is_some() || style != "solid" {
+            ctx.report_unsupported("cancel.stroke");
+        }
+        let Smart::Custom(Paint::Solid(color)) = stroke.paint else {
+            return menclose.with_children(body).into();
+        };
+        // The lines have the color of `menclose`, the body keeps the color of the
+        // text.
+        let mut body = Element::new("mrow").with_children(body);
+        if let Paint::Solid(fill) = TextElem::fill_in(style_chain) {
+            body.set_attr("mathcolor", fill.to_hex());
+        }
+        return menclose
+            .with_attr("mathcolor", color.to_hex())
+            .with_child(body)
+            .into();
+    }
+
+    // `menclose` is not supported in MathML Core, but FireFox support it.
+    // For other browsers, the lines are drawn by the CSS in `css::POLYFILL`.
+    let prefix = &ctx.options.class_prefix;
+    let mut class = format!("{}-cancel", prefix);
+    if style != "solid" {
+        class = format!("{} {}-cancel-{}", class, prefix, style);
+    }
+    let mut menclose = menclose.with_attr("class", class).with_children(body);
+
+    let mut properties = Vec::new();
+    if let Smart::Custom(Paint::Solid(color)) = stroke.paint {
+        properties.push(format!("--{}-cancel-color: {}", prefix, color.to_hex()));
+    }
+    if let Some(thickness) = thickness {
+        properties.push(format!(
+            "--{}-cancel-thickness: {}",
+            prefix,
+            length::em(thickness, style_chain)
+        ));
+    }
+    if style != "solid" {
+        properties.push(format!("--{}-cancel-style: {}", prefix, style));
+    }
+    if !properties.is_empty() {
+        menclose.set_attr("style", properties.join("; "));
+    }
+
+    for mirrored in lines {
+        let line = match angle {
+            Some(angle) => {
+                let angle = if mirrored { -angle } else { angle };
+                Element::new("mrow")
+                    .with_attr("class", format!("{}-cancel-line", prefix))
+                    .with_attr(
+                        "style",
+                        format!(
+                            "height: {}; transform: translate(-50%, -50%) rotate({}deg)",
+                            length::rel(line_length, style_chain),
+                            length::number(angle)
+                        ),
+                    )
+            }
+            None => {
+                // The diagonal spans the whole body, so only the relative part of
+                // the length can be honored.
+                let mut transforms = Vec::new();
+                if line_length.rel != Ratio::one() {
+                    transforms.push(format!("scale({})", length::number(line_length.rel.get())));
+                }
+                if mirrored {
+                    transforms.push("scaleX(-1)".to_string());
+                }
+
+                let placeholder = Element::new("mrow")
+                    .with_attr("class", format!("{}-cancel-wrapper-placeholder", prefix));
+                if transforms.is_empty() {
+                    placeholder
+                } else {
+                    placeholder.with_attr("style", format!("transform: {}", transforms.join(" ")))
+                }
+            }
+        };
+        menclose.children.push(line.into());
+    }
+
+    menclose.into()
+}
+
+fn process_op(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<OpElem>()
         .expect("Type conversion to `OpElem` must be successful.");
 
     let text = coerced.text();
 
-    // FIXME: MathML Core recommends to use <mi> to describe functions rather than <mo>.
+    // Named operators such as `sin` are function names, which MathML Core describes
+    // with upright identifiers. `process_sequence` adds the function application.
     if text.elem().name() == "text" {
-        format!("<mo>{}</mo>", escape_helper(&coerced.text().plain_text()))
+        let name = text.plain_text().to_string();
+        let is_single = name.chars().count() == 1;
+        identifier(name, is_single)
     } else {
-        format!("<mo>{}</mo>", convert_to_mathml_impl(text, style_chain))
+        Element::new("mrow")
+            .with_children(convert_to_mathml_impl(ctx, text, style_chain))
+            .into()
     }
 }
 
-fn process_lr(content: &Content, style_chain: StyleChain) -> String {
+fn process_lr(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<LrElem>()
         .expect("Type conversion to `LrElem` must be successful.");
 
     let size = coerced.size(style_chain);
+    let mut body = coerced.body();
+    // Like Typst, `lr((x))` scales the delimiters of the inner `lr`.
+    if let Some(inner) = body.to_packed::<LrElem>() {
+        body = inner.body();
+    }
+
+    let Some(sequence) = body.to_packed::<SequenceElem>() else {
+        return Element::new("mrow")
+            .with_children(convert_to_mathml_impl(ctx, body, style_chain))
+            .into();
+    };
+
+    // The delimiters are the first and last child of the body, e.g. `(`, `x` and `)`.
+    let delimiter = |child: &Content, form: &str| -> Option<Node> {
+        let Some(delim) = child.to_packed::<TextElem>() else {
+            return convert_to_mathml_impl(ctx, child, style_chain);
+        };
+        let fence = Element::new("mo")
+            .with_attr("fence", "true")
+            .with_attr("form", form)
+            .with_attr("stretchy", "true")
+            .with_attr("symmetric", "true")
+            .with_text(delim.text().as_str());
+        Some(sized_operator(fence.into(), size, style_chain))
+    };
+    let Some((open, rest)) = sequence.children().split_first() else {
+        return Element::new("mrow").into();
+    };
+    let (close, inner) = match rest.split_last() {
+        Some((close, inner)) => (Some(close), inner),
+        None => (None, rest),
+    };
+
+    let mut rows = convert_aligned_children(ctx, inner, style_chain);
+    for (child, node) in rows.iter_mut().flatten().flatten() {
+        if child.is::<MidElem>() {
+            let mid = std::mem::replace(node, Node::Text(String::new()));
+            *node = sized_operator(mid, size, style_chain);
+        }
+    }
+    // Like in Typst, the delimiters span all rows of a body with linebreaks.
+    let inner = if rows.len() == 1 {
+        rows.into_iter().flatten().flatten().collect()
+    } else {
+        let mut table = Element::new("mtable");
+        table.children = aligned_rows(ctx, rows, FixedAlignment::Center);
+        align_columns(ctx, &mut table);
+        vec![(body, table.into())]
+    };
+
+    let mut row = Vec::new();
+    row.extend(delimiter(open, "prefix").map(|node| (open, node)));
+    row.extend(inner);
+    if let Some(close) = close {
+        row.extend(delimiter(close, "postfix").map(|node| (close, node)));
+    }
+
+    Element::new("mrow")
+        .with_children(finish_row(ctx, row))
+        .into()
+}
+
+/// Set the size of a stretchy operator, relative to the content it wraps or
+/// spans, e.g. of a delimiter or an accent.
+///
+/// The default size of 100% is what renderers do anyway and is left out.
+fn sized_operator(mut node: Node, size: Rel<Abs>, style_chain: StyleChain) -> Node {
+    if size == Rel::one() {
+        return node;
+    }
+
+    if let Some(mo) = node.as_element_mut().filter(|element| element.name == "mo") {
+        let size = length::rel(size, style_chain);
+        mo.set_attr("minsize", size.clone());
+        mo.set_attr("maxsize", size);
+    }
+
+    node
+}
+
+/// MDN Reference: https://developer.mozilla.org/en-US/docs/Web/MathML/Element/mover#examples
+fn process_accent(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
+    let coerced = content
+        .to_packed::<AccentElem>()
+        .expect("Type conversion to `AccentElem` must be successful.");
+
+    let base = convert_required(ctx, coerced.base(), style_chain);
+    let combining = coerced.accent().0;
+    let accent = style::accent::spacing_char(combining);
+    let size = coerced.size(style_chain);
+
+    let mut mo = Element::new("mo").with_text(accent.to_string());
+    // A base of several atoms, e.g. `arrow(A B C)`, needs a wide accent.
+    let wide = base
+        .as_element()
+        .is_some_and(|element| element.name == "mrow" && element.children.len() > 1);
+    if (wide || size != Rel::one()) && style::accent::is_wide(accent) {
+        mo.set_attr("stretchy", "true");
+    }
 
-    format!(
-        "<mrow>{}</mrow>",
-        convert_to_mathml_impl(coerced.body(), style_chain)
-    )
+    let (name, attr) = if style::accent::is_below(combining) {
+        ("munder", "accentunder")
+    } else {
+        ("mover", "accent")
+    };
+    Element::new(name)
+        .with_attr(attr, "true")
+        .with_child(base)
+        .with_child(sized_operator(mo.into(), size, style_chain))
+        .into()
 }
 
-fn process_equation(content: &Content, style_chain: StyleChain) -> String {
+fn process_equation(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
     let coerced = content
         .to_packed::<EquationElem>()
         .expect("Type conversion to `EquationElem` must be successful.");
 
     // FIXME: need more processing?
-    convert_to_mathml_impl(coerced.body(), style_chain)
+    convert_to_mathml_impl(ctx, coerced.body(), style_chain)
 }
 
 /// MDN Reference: https://developer.mozilla.org/en-US/docs/Web/MathML/Guides/Tables#usage_for_advanced_layout
-fn process_cases(content: &Content, style_chain: StyleChain) -> String {
+fn process_cases(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<CasesElem>()
         .expect("Type conversion to `CasesElem` must be successful.");
@@ -408,68 +910,32 @@ fn process_cases(content: &Content, style_chain: StyleChain) -> String {
     let gap = coerced.gap(style_chain);
     let children = coerced.children();
 
-    let mtd_left_str = r#"<mtd style="text-align: left">"#;
-
-    let mut res = format!("<mrow>");
+    let mut res = Element::new("mrow");
     if !reverse && delim.open().is_some() {
-        res.push_str("<mo>");
-        res.push(delim.open().unwrap());
-        res.push_str("</mo>");
+        res.children
+            .push(Element::new("mo").with_text(delim.open().unwrap()).into());
     }
-    res.push_str("<mtable>");
 
+    let mut rows = Vec::new();
     for child in children {
-        let child_type = child.elem().name();
-
-        res.push_str("<mtr>");
-        if child_type == "sequence" {
-            let child_coerced = child
-                .to_packed::<SequenceElem>()
-                .expect("Type conversion to `SequenceElem` must be successful.");
-            let mut tmp = child_coerced.children().iter();
-            let first = tmp
-                .next()
-                .and_then(|it| Some(convert_to_mathml_impl(it, style_chain)));
-            if let Some(first) = first {
-                res.push_str(mtd_left_str);
-                res.push_str(&first);
-                res.push_str("</mtd>");
-            }
-            let rest: String = tmp
-                .map(|each| {
-                    if each.elem().name() == "space" {
-                        "<mtext>&nbsp;</mtext>".to_string()
-                    } else {
-                        convert_to_mathml_impl(each, style_chain)
-                    }
-                })
-                .collect();
-            if rest.len() > 0 {
-                res.push_str(mtd_left_str);
-                res.push_str(&rest);
-                res.push_str("</mtd>");
-            }
-        } else {
-            res.push_str(mtd_left_str);
-            res.push_str(&convert_to_mathml_impl(child, style_chain));
-            res.push_str("</mtd>");
-        }
-        res.push_str("</mtr>");
+        rows.extend(convert_aligned(ctx, child, style_chain));
     }
+    let mut table = Element::new("mtable");
+    table.children = aligned_rows(ctx, rows, FixedAlignment::Start);
+    table_spacing(ctx, &mut table, gap, None, style_chain);
+    align_columns(ctx, &mut table);
+    res.children.push(table.into());
 
-    res.push_str("</mtable>");
     if reverse && delim.close().is_some() {
-        res.push_str("<mo>");
-        res.push(delim.close().unwrap());
-        res.push_str("</mo>");
+        res.children
+            .push(Element::new("mo").with_text(delim.close().unwrap()).into());
     }
-    res.push_str("</mrow>");
 
-    res
+    res.into()
 }
 
 /// MDN Reference: https://developer.mozilla.org/en-US/docs/Web/MathML/Element/mtd#matrix_using_mtable_mrow_mtr_and_mtd
-fn process_mat(content: &Content, style_chain: StyleChain) -> String {
+fn process_mat(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<MatElem>()
         .expect("Type conversion to `MatElem` must be successful.");
@@ -481,45 +947,91 @@ fn process_mat(content: &Content, style_chain: StyleChain) -> String {
     let column_gap = coerced.column_gap(style_chain);
     let rows = coerced.rows();
 
-    // FIXME: It seems that CSS align does not work in Chrome.
-    let mtd_left_str = match align {
-        FixedAlignment::Start => r#"<mtd style="text-align: left">"#,
-        FixedAlignment::Center => r#"<mtd style="text-align: center">"#,
-        FixedAlignment::End => r#"<mtd style="text-align: right">"#,
-    };
-
-    let mut res = format!("<mrow>");
+    let mut res = Element::new("mrow");
     if delim.open().is_some() {
-        res.push_str(r#"<mo form="prefix">"#);
-        res.push(delim.open().unwrap());
-        res.push_str("</mo>");
+        res.children.push(
+            Element::new("mo")
+                .with_attr("form", "prefix")
+                .with_text(delim.open().unwrap())
+                .into(),
+        );
     }
-    res.push_str("<mtable>");
-
-    for row in rows {
-        res.push_str("<mtr>");
 
-        for item in row {
-            res.push_str(mtd_left_str);
-            res.push_str(&convert_to_mathml_impl(item, style_chain));
-            res.push_str("</mtd>");
+    // Like Typst, the cells of each column are aligned at their alignment points,
+    // each column is split into as many columns as it has parts between them.
+    let cells: Vec<Vec<Option<AlignedRow>>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    has_alignment(cell)
+                        .then(|| aligned_line(convert_aligned(ctx, cell, style_chain)))
+                })
+                .collect()
+        })
+        .collect();
+    let mut groups = vec![1; rows.iter().map(|row| row.len()).max().unwrap_or(0)];
+    for row in &cells {
+        for (column, cell) in row.iter().enumerate() {
+            groups[column] = groups[column].max(cell.as_ref().map_or(1, Vec::len));
         }
+    }
 
-        res.push_str("</mtr>");
+    let mut table = Element::new("mtable");
+    for (row, cells) in rows.iter().zip(cells) {
+        let mut tr = Element::new("mtr");
+        for ((item, cell), &columns) in row.iter().zip(cells).zip(&groups) {
+            let mut tds = match cell {
+                Some(parts) => finish_cells(ctx, parts)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, nodes)| mtd(ctx, column_alignment(idx, columns, align), nodes))
+                    .collect(),
+                None => {
+                    let cell = convert_to_mathml_impl(ctx, item, style_chain);
+                    vec![mtd(ctx, column_alignment(0, columns, align), cell)]
+                }
+            };
+            while tds.len() < columns {
+                tds.push(mtd(ctx, column_alignment(tds.len(), columns, align), []));
+            }
+            tr.children.extend(tds.into_iter().map(Node::from));
+        }
+        table.children.push(tr.into());
+    }
+    table_spacing(ctx, &mut table, row_gap, Some(column_gap), style_chain);
+    if let Some(augment) = augment {
+        table_augment(
+            ctx,
+            &mut table,
+            &augment,
+            rows.len(),
+            &groups,
+            style_chain,
+            content.span(),
+        );
     }
+    align_column_groups(
+        ctx,
+        &mut table,
+        &groups,
+        Some(length::rel(column_gap, style_chain)),
+    );
+    res.children.push(table.into());
 
-    res.push_str("</mtable>");
     if delim.close().is_some() {
-        res.push_str(r#"<mo form="postfix">"#);
-        res.push(delim.close().unwrap());
-        res.push_str("</mo>");
+        res.children.push(
+            Element::new("mo")
+                .with_attr("form", "postfix")
+                .with_text(delim.close().unwrap())
+                .into(),
+        );
     }
-    res.push_str("</mrow>");
 
-    res
+    res.into()
 }
 
-fn process_vec(content: &Content, style_chain: StyleChain) -> String {
+fn process_vec(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<VecElem>()
         .expect("Type conversion to `VecElem` must be successful.");
@@ -529,504 +1041,1356 @@ fn process_vec(content: &Content, style_chain: StyleChain) -> String {
     let gap = coerced.gap(style_chain);
     let children = coerced.children();
 
-    // FIXME: It seems that CSS align does not work in Chrome.
-    let mtd_left_str = match align {
-        FixedAlignment::Start => r#"<mtd style="text-align: left">"#,
-        FixedAlignment::Center => r#"<mtd style="text-align: center">"#,
-        FixedAlignment::End => r#"<mtd style="text-align: right">"#,
-    };
-
-    let mut res = format!("<mrow>");
+    let mut res = Element::new("mrow");
     if delim.open().is_some() {
-        res.push_str(r#"<mo form="prefix">"#);
-        res.push(delim.open().unwrap());
-        res.push_str("</mo>");
+        res.children.push(
+            Element::new("mo")
+                .with_attr("form", "prefix")
+                .with_text(delim.open().unwrap())
+                .into(),
+        );
     }
-    res.push_str("<mtable>");
 
+    let mut rows = Vec::new();
     for child in children {
-        res.push_str("<mtr>");
-        res.push_str(mtd_left_str);
-        res.push_str(&convert_to_mathml_impl(child, style_chain));
-        res.push_str("</mtd></mtr>");
+        rows.extend(convert_aligned(ctx, child, style_chain));
     }
+    let mut table = Element::new("mtable");
+    table.children = aligned_rows(ctx, rows, align);
+    table_spacing(ctx, &mut table, gap, None, style_chain);
+    align_columns(ctx, &mut table);
+    res.children.push(table.into());
 
-    res.push_str("</mtable>");
     if delim.close().is_some() {
-        res.push_str(r#"<mo form="postfix">"#);
-        res.push(delim.close().unwrap());
-        res.push_str("</mo>");
+        res.children.push(
+            Element::new("mo")
+                .with_attr("form", "postfix")
+                .with_text(delim.close().unwrap())
+                .into(),
+        );
     }
-    res.push_str("</mrow>");
 
-    res
+    res.into()
 }
 
-fn process_class(content: &Content, style_chain: StyleChain) -> String {
+fn process_class(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<ClassElem>()
         .expect("Type conversion to `ClassElem` must be successful.");
 
     let math_class = coerced.class();
-    let text = if coerced.body().elem().name() == "text" {
-        coerced.body().plain_text().to_string()
+    if coerced.body().elem().name() == "text" {
+        let text = coerced.body().plain_text().to_string();
+        math_class_helper(Node::Text(text.clone()), &text, math_class, style_chain)
     } else {
-        convert_to_mathml_impl(coerced.body(), style_chain)
-    };
-
-    math_class_helper(&text, None, math_class, style_chain)
+        let body = convert_required(ctx, coerced.body(), style_chain);
+        math_class_helper(body, "", math_class, style_chain)
+    }
 }
 
-fn process_primes(content: &Content) -> String {
+fn process_primes(content: &Content) -> Node {
     let coerced = content
         .to_packed::<PrimesElem>()
         .expect("Type conversion to `PrimesElem` must be successful.");
 
-    let count = *coerced.count();
+    // Like Typst, use the glyphs for up to four primes and repeat the single prime
+    // beyond.
+    let text = match *coerced.count() {
+        1 => "′".to_string(),
+        2 => "″".to_string(),
+        3 => "‴".to_string(),
+        4 => "⁗".to_string(),
+        count => "′".repeat(count),
+    };
+
+    Element::new("mo").with_text(text).into()
+}
 
-    format!("<mo>{}</mo>", "&#x2032;".repeat(count))
+/// Attach `primes` as the superscript of `base`, before its superscript if it has
+/// one already.
+fn attach_primes(base: &mut Node, primes: Node) {
+    *base = match std::mem::replace(base, Node::Text(String::new())) {
+        Node::Element(mut element) if element.name == "msup" || element.name == "msubsup" => {
+            let sup = element
+                .children
+                .pop()
+                .expect("A superscript must have a script.");
+            element.children.push(
+                Element::new("mrow")
+                    .with_child(primes)
+                    .with_child(sup)
+                    .into(),
+            );
+            element.into()
+        }
+        Node::Element(mut element) if element.name == "msub" => {
+            element.name = "msubsup".to_string();
+            element.children.push(primes);
+            element.into()
+        }
+        node => Element::new("msup")
+            .with_child(node)
+            .with_child(primes)
+            .into(),
+    };
 }
 
-fn process_styled(content: &Content, style_chain: StyleChain) -> String {
+fn process_styled(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
     let coerced = content
         .to_packed::<StyledElem>()
         .expect("Type conversion to `StyledElem` must be successful.");
 
+    let size = styled_size(ctx, coerced.styles());
     let style_chain = coerced.styles().chain(&style_chain);
     let child = coerced.child();
 
-    convert_to_mathml_impl(child, style_chain)
+    ctx.at_size(size, || convert_to_mathml_impl(ctx, child, style_chain))
+}
+
+/// The size `styles` set for their content, e.g. with `display` or `inline`, the
+/// current size if they set none.
+fn styled_size(ctx: &Context, styles: &Styles) -> MathSize {
+    let mut outer = Styles::new();
+    outer.set(EquationElem::set_size(ctx.size.get()));
+    EquationElem::size_in(styles.chain(&StyleChain::new(&outer)))
+}
+
+/// The size of `content`, which may set its own, e.g. `display(sum)`.
+fn content_size(ctx: &Context, content: &Content) -> MathSize {
+    match content.to_packed::<StyledElem>() {
+        Some(styled) => ctx.at_size(styled_size(ctx, styled.styles()), || {
+            content_size(ctx, styled.child())
+        }),
+        None => ctx.size.get(),
+    }
 }
 
-fn process_h(content: &Content) -> String {
+fn process_h(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
     let coerced = content
         .to_packed::<HElem>()
         .expect("Type conversion to `HElem` must be successful.");
 
-    let spacing = coerced.amount();
-    let spacing_str = if let Spacing::Rel(rel) = spacing {
-        format!("{:?}", rel)
-    } else {
-        "".to_string()
-    };
-
-    if spacing_str.ends_with("em") {
-        format!(r#"<mspace width="{}"></mspace>"#, spacing_str)
-    } else {
-        format!(r#"<mspace width="0.333em"></mspace>"#)
+    match coerced.amount() {
+        // Like Typst, ignore relative spacing in math, there is no region to be
+        // relative to.
+        Spacing::Rel(rel) if !rel.rel.is_zero() => None,
+        Spacing::Rel(rel) => {
+            let width = length::to_em(rel.abs.resolve(style_chain), style_chain);
+            let space = Element::new("mspace");
+            if width >= 0.0 || ctx.options.profile == TargetProfile::Full {
+                Some(
+                    space
+                        .with_attr("width", format!("{}em", length::number(width)))
+                        .into(),
+                )
+            } else {
+                // MathML Core does not allow a negative width, pull the following
+                // content back instead.
+                let style = format!("margin-left: {}em;", length::number(width));
+                Some(space.with_attr("style", style).into())
+            }
+        }
+        // Fractional spacing fills the row, with CSS as there is no flexible
+        // spacing in MathML.
+        Spacing::Fr(fr) => {
+            let space = Element::new("mspace")
+                .with_attr("class", format!("{}-fr", ctx.options.class_prefix));
+            if ctx.options.profile == TargetProfile::Core {
+                let style = format!("flex-grow: {};", length::number(fr.get()));
+                Some(space.with_attr("style", style).into())
+            } else {
+                // Without the polyfill CSS, the spacing is lost.
+                ctx.report_unsupported("h.fr");
+                Some(space.with_attr("width", "0em").into())
+            }
+        }
     }
 }
 
-fn process_hide(content: &Content, style_chain: StyleChain) -> String {
+fn process_hide(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<HideElem>()
         .expect("Type conversion to `HideElem` must be successful.");
 
     let body = coerced.body();
 
-    format!(
-        r#"<mrow style="visibility: hidden;">{}</mrow>"#,
-        convert_to_mathml_impl(body, style_chain)
-    )
+    Element::new("mrow")
+        .with_attr("style", "visibility: hidden;")
+        .with_children(convert_to_mathml_impl(ctx, body, style_chain))
+        .into()
+}
+
+fn process_stretch(ctx: &Context, content: &Content, style_chain: StyleChain) -> Option<Node> {
+    stretch(ctx, content, style_chain, false)
 }
 
-fn process_stretch(content: &Content, style_chain: StyleChain) -> String {
+/// Convert a `stretch`, `attached` whether it is the base of attachments, over or
+/// under which it stretches.
+fn stretch(
+    ctx: &Context,
+    content: &Content,
+    style_chain: StyleChain,
+    attached: bool,
+) -> Option<Node> {
     let coerced = content
         .to_packed::<StretchElem>()
         .expect("Type conversion to `StretchElem` must be successful.");
 
-    // FIXME: Sadly, it seems that setting horizontal size for stretchy math operator
-    // does not work in browsers now, maybe i can revisit it in the future.
     let size = coerced.size(style_chain);
     let body = coerced.body();
 
-    if body.elem().name() == "text" {
-        format!(
-            r#"<mo stretchy="true">{}</mo>"#,
-            escape_helper(body.plain_text().to_string().as_str())
-        )
-    } else {
-        convert_to_mathml_impl(body, style_chain)
+    // The stretched glyph is an operator, so that it stretches over or under its
+    // attachments, e.g. `stretch(->)^"text"`. Other content, e.g. `stretch(a + b)`,
+    // is kept as is.
+    let mut node = match body.to_packed::<TextElem>() {
+        Some(text) => Element::new("mo").with_text(text.text().as_str()).into(),
+        None => convert_to_mathml_impl(ctx, body, style_chain)?,
+    };
+    let Some(mo) = node.as_element_mut().filter(|element| element.name == "mo") else {
+        return Some(node);
+    };
+    mo.set_attr("stretchy", "true");
+
+    // The width of an operator stretched over its attachments is only known to the
+    // renderer, which may then scale it relative to that width.
+    if size == Rel::one() || ctx.options.profile == TargetProfile::Full || attached {
+        return Some(sized_operator(node, size, style_chain));
     }
+
+    // Browsers ignore `minsize` and `maxsize` of horizontally stretched operators,
+    // so scale them with CSS instead, taking their width as 1em to make room.
+    let width = size.rel.get() + length::to_em(size.abs, style_chain);
+    push_style(
+        mo,
+        &format!(
+            "transform: scaleX({}); margin: 0 {}em",
+            length::number(width),
+            length::number((width - 1.0) / 2.0)
+        ),
+    );
+    Some(node)
 }
 
-fn process_sequence(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<SequenceElem>()
-        .expect("Type conversion to `SequenceElem` must be successful.");
-    let children = coerced.children();
+fn process_sequence(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
+    let rows = convert_aligned(ctx, content, style_chain);
 
-    let mtd_left_align_begin = r#"<mtd style="text-align: left">"#;
-    let mtd_right_align_begin = r#"<mtd style="text-align: right">"#;
-    let mtd_center_align_begin = r#"<mtd style="text-align: center">"#;
+    // Alignment points only have an effect across several rows.
+    if rows.len() == 1 {
+        let row = rows.into_iter().flatten().flatten().collect();
+        return Element::new("mrow")
+            .with_children(finish_row(ctx, row))
+            .into();
+    }
 
-    let children_split: Vec<_> = children
-        .split(|each| each.elem().name() == "linebreak")
-        .map(|each| {
-            each.split(|it| it.elem().name() == "align-point")
-                .collect::<Vec<_>>()
-        })
-        .collect();
+    let mut table = Element::new("mtable");
+    table.children = aligned_rows(ctx, rows, FixedAlignment::Center);
+    align_columns(ctx, &mut table);
+    table.into()
+}
 
-    let combine_str = |it: Iter<Content>| {
-        it.map(|child| convert_to_mathml_impl(child, style_chain))
-            .collect::<String>()
-    };
+/// A row of converted content, split into columns at alignment points.
+type AlignedRow<'a> = Vec<Vec<(&'a Content, Node)>>;
 
-    let is_no_align_point = children_split
-        .iter()
-        .map(|each| each.len())
-        .all(|num| num == 1);
+/// Convert `content` into rows, split at linebreaks, of columns, split at
+/// alignment points, e.g. `a &= b \ &= c`. Like Typst, the alignment points and
+/// linebreaks of nested sequences and styled content count as well.
+fn convert_aligned<'a>(
+    ctx: &Context,
+    content: &'a Content,
+    style_chain: StyleChain,
+) -> Vec<AlignedRow<'a>> {
+    let mut rows = vec![vec![vec![]]];
+    collect_aligned(ctx, content, style_chain, &mut rows);
+    trim_rows(rows)
+}
 
-    if children_split.len() > 1 {
-        let mut res = "<mtable>".to_string();
+/// Like [`convert_aligned`], for the children of a sequence, e.g. the body of `lr`
+/// between its delimiters.
+fn convert_aligned_children<'a>(
+    ctx: &Context,
+    children: &'a [Content],
+    style_chain: StyleChain,
+) -> Vec<AlignedRow<'a>> {
+    let mut rows = vec![vec![vec![]]];
+    collect_aligned_children(ctx, children, style_chain, &mut rows);
+    trim_rows(rows)
+}
 
-        for row in children_split {
-            res.push_str("<mtr>");
+/// Remove the empty row after a linebreak at the very end, which does not start
+/// another row.
+fn trim_rows(mut rows: Vec<AlignedRow>) -> Vec<AlignedRow> {
+    if rows.len() > 1 && rows.last().is_some_and(|row| row.iter().all(Vec::is_empty)) {
+        rows.pop();
+    }
+    rows
+}
 
-            for (idx, item) in row.iter().enumerate() {
-                if is_no_align_point {
-                    res.push_str(mtd_center_align_begin);
-                } else if idx % 2 == 0 {
-                    res.push_str(mtd_right_align_begin);
-                } else {
-                    res.push_str(mtd_left_align_begin);
-                }
+fn collect_aligned<'a>(
+    ctx: &Context,
+    content: &'a Content,
+    style_chain: StyleChain,
+    rows: &mut Vec<AlignedRow<'a>>,
+) {
+    if let Some(styled) = content
+        .to_packed::<StyledElem>()
+        .filter(|_| has_alignment(content))
+    {
+        let style_chain = styled.styles().chain(&style_chain);
+        collect_aligned(ctx, styled.child(), style_chain, rows);
+        return;
+    }
+
+    let Some(sequence) = content.to_packed::<SequenceElem>() else {
+        if let Some(node) = convert_to_mathml_impl(ctx, content, style_chain) {
+            current_column(rows).push((content, node));
+        }
+        return;
+    };
+    collect_aligned_children(ctx, sequence.children(), style_chain, rows);
+}
+
+/// Like [`collect_aligned`], for the children of a sequence.
+fn collect_aligned_children<'a>(
+    ctx: &Context,
+    children: &'a [Content],
+    style_chain: StyleChain,
+    rows: &mut Vec<AlignedRow<'a>>,
+) {
+    let mut rest = children;
+    while let Some((child, tail)) = rest.split_first() {
+        if let Some((len, number)) = number::merge(rest, ctx.options.decimal_separator) {
+            current_column(rows).push((child, Element::new("mn").with_text(number).into()));
+            rest = &rest[len..];
+            continue;
+        }
+        rest = tail;
+
+        if child.is::<LinebreakElem>() {
+            rows.push(vec![vec![]]);
+            continue;
+        }
+        if child.is::<AlignPointElem>() {
+            rows.last_mut()
+                .expect("There is always a current row.")
+                .push(vec![]);
+            continue;
+        }
+        if has_alignment(child) {
+            collect_aligned(ctx, child, style_chain, rows);
+            continue;
+        }
 
-                res.push_str(&combine_str(item.iter()));
+        let column = current_column(rows);
 
-                res.push_str("</mtd>");
+        // Primes after their base, e.g. from `#primes(2)`, are attached to it.
+        if child.is::<PrimesElem>() {
+            if column
+                .last()
+                .is_some_and(|(last, _)| last.is::<SpaceElem>())
+            {
+                column.pop();
             }
+            if let Some((_, last)) = column.last_mut() {
+                attach_primes(last, process_primes(child));
+                continue;
+            }
+        }
 
-            res.push_str("</mtr>");
+        // Spaces are kept by `finish_row` where Typst renders them.
+        let node = if child.is::<SpaceElem>() {
+            Some(
+                Element::new("mspace")
+                    .with_attr("width", SPACE_WIDTH)
+                    .into(),
+            )
+        } else {
+            convert_to_mathml_impl(ctx, child, style_chain)
+        };
+        column.extend(node.map(|node| (child, node)));
+    }
+}
+
+/// Join aligned rows into one line, e.g. for a matrix cell, which has no
+/// linebreaks.
+fn aligned_line(rows: Vec<AlignedRow>) -> AlignedRow {
+    let mut line: AlignedRow = Vec::new();
+    for row in rows {
+        let mut columns = row.into_iter();
+        match (line.last_mut(), columns.next()) {
+            (Some(last), Some(first)) => last.extend(first),
+            (None, Some(first)) => line.push(first),
+            _ => {}
         }
+        line.extend(columns);
+    }
+    line
+}
 
-        res.push_str("</mtable>");
+/// The column new content of aligned rows goes to.
+fn current_column<'r, 'a>(rows: &'r mut [AlignedRow<'a>]) -> &'r mut Vec<(&'a Content, Node)> {
+    rows.last_mut()
+        .and_then(|row| row.last_mut())
+        .expect("There is always a current column.")
+}
 
-        res
+/// Whether `content` has alignment points or linebreaks, possibly in nested
+/// sequences and styled content.
+fn has_alignment(content: &Content) -> bool {
+    if content.is::<AlignPointElem>() || content.is::<LinebreakElem>() {
+        true
+    } else if let Some(styled) = content.to_packed::<StyledElem>() {
+        has_alignment(styled.child())
+    } else if let Some(sequence) = content.to_packed::<SequenceElem>() {
+        sequence.children().iter().any(has_alignment)
     } else {
-        format!("<mrow>{}</mrow>", combine_str(children.iter()))
+        false
     }
 }
 
-fn process_underline(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<UnderlineElem>()
-        .expect("Type conversion to `UnderlineElem` must be successful.");
+/// The cells of aligned rows, padded to the same number of columns. Like Typst,
+/// the columns between alignment points alternate between right and left
+/// alignment, so that pairs of them meet at the points. Without alignment points,
+/// the rows are aligned by `align`.
+fn aligned_rows(ctx: &Context, rows: Vec<AlignedRow>, align: FixedAlignment) -> Vec<Node> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(1);
+    rows.into_iter()
+        .map(|row| {
+            let padding = columns - row.len();
+            let cells = finish_cells(ctx, row)
+                .into_iter()
+                .chain(std::iter::repeat_with(Vec::new).take(padding))
+                .enumerate()
+                .map(|(idx, nodes)| mtd(ctx, column_alignment(idx, columns, align), nodes).into());
+            Element::new("mtr").with_children(cells).into()
+        })
+        .collect()
+}
 
-    let body = coerced.body();
+/// The alignment of column `idx` of `columns` split at alignment points.
+fn column_alignment(idx: usize, columns: usize, align: FixedAlignment) -> FixedAlignment {
+    if columns == 1 {
+        align
+    } else if idx % 2 == 0 {
+        FixedAlignment::End
+    } else {
+        FixedAlignment::Start
+    }
+}
 
-    // format!(
-    //     r#"<munder>{}<mo stretchy="true">&#x332;</mo></munder>"#,
-    //     convert_to_mathml_impl(body, style_chain)
-    // )
+/// Remove the spacing between the columns of `table` split at alignment points,
+/// as the content on both sides of a point is continuous.
+fn align_columns(ctx: &Context, table: &mut Element) {
+    let groups = vec![table_columns(table)];
+    align_column_groups(ctx, table, &groups, None);
+}
 
-    // FIXME: Now Chrome does not respect <munder> with underline strechy operater. I opened a
-    // bug report https://issues.chromium.org/issues/386610915. When the bug is resolved, this
-    // should switch to <munder>.
-    format!(
-        r#"<mrow style="border-bottom: 1px solid currentColor; display: inline-block;">{}</mrow>"#,
-        convert_to_mathml_impl(body, style_chain)
-    )
+/// The number of columns of `table`.
+fn table_columns(table: &Element) -> usize {
+    table
+        .children
+        .iter()
+        .filter_map(Node::as_element)
+        .map(|tr| tr.children.len())
+        .max()
+        .unwrap_or(0)
 }
 
-fn process_overline(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<OverlineElem>()
-        .expect("Type conversion to `OverlineElem` must be successful.");
+/// Remove the spacing between the columns of each group of columns of `table`
+/// split at alignment points. `groups` are the number of columns of each group,
+/// `gap` the spacing between groups for `columnspacing`.
+fn align_column_groups(ctx: &Context, table: &mut Element, groups: &[usize], gap: Option<String>) {
+    if groups.iter().all(|&columns| columns <= 1) {
+        return;
+    }
 
-    let body = coerced.body();
+    // For each column, whether it is the first and the last of its group.
+    let bounds: Vec<(bool, bool)> = groups
+        .iter()
+        .flat_map(|&columns| (0..columns).map(move |idx| (idx == 0, idx + 1 == columns)))
+        .collect();
 
-    // FIXME: Now Chrome does not respect <mover> with overline strechy operater. I opened a
-    // bug report https://issues.chromium.org/issues/386610915. When the bug is resolved, this
-    // should switch to <mover>.
-    format!(
-        r#"<mrow style="border-top: 1px solid currentColor; display: inline-block;">{}</mrow>"#,
-        convert_to_mathml_impl(body, style_chain)
-    )
+    let gap = gap.unwrap_or_else(|| "0.8em".to_string());
+    let spacing = bounds
+        .iter()
+        .skip(1)
+        .map(|&(first, _)| if first { gap.as_str() } else { "0" })
+        .collect::<Vec<_>>();
+    if spacing.iter().all(|&it| it == "0") {
+        table.set_attr("columnspacing", "0");
+    } else {
+        table.set_attr("columnspacing", spacing.join(" "));
+    }
+    if ctx.options.profile == TargetProfile::Full {
+        return;
+    }
+
+    // MathML Core ignores `columnspacing`, remove the padding of the cells at the
+    // alignment points instead.
+    for (_, column, cell) in cells_mut(table) {
+        let Some(&(first, last)) = bounds.get(column) else {
+            continue;
+        };
+        if !first {
+            push_style(cell, "padding-left: 0");
+        }
+        if !last {
+            push_style(cell, "padding-right: 0");
+        }
+    }
 }
 
-fn process_underbrace(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<UnderbraceElem>()
-        .expect("Type conversion to `UnderbraceElem` must be successful.");
+/// Resolve the forms of operators, make implicit operations explicit and apply the
+/// spacing mode in a row of converted children.
+fn finish_row(ctx: &Context, mut row: Vec<(&Content, Node)>) -> Vec<Node> {
+    resolve_forms(&mut row);
+    let row = keep_spaces(ctx, row);
+    let mut nodes = insert_invisible_operators(row);
+    if ctx.options.spacing == SpacingMode::Typst {
+        apply_spacing(ctx, &mut nodes);
+    }
+    nodes
+}
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+/// Like [`finish_row`], for a row split into cells at alignment points. The content
+/// on both sides of a point is continuous, so the row is finished as a whole, with
+/// `<malignmark>` breaks between the cells, and split into its cells after.
+fn finish_cells(ctx: &Context, row: AlignedRow) -> Vec<Vec<Node>> {
+    if row.is_empty() {
+        return Vec::new();
+    }
 
-    let underbrace = if let Some(annotation) = annotation {
-        format!(
-            r#"<munder><mo stretchy="true">&#x23DF;</mo><mtext>{}</mtext></munder>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x23DF;</mo>"#.to_string()
-    };
+    let point = AlignPointElem::new().pack();
+    let mut items: Vec<(&Content, Node)> = Vec::new();
+    for (idx, cell) in row.into_iter().enumerate() {
+        if idx > 0 {
+            items.push((&point, Element::new("malignmark").into()));
+        }
+        items.extend(cell);
+    }
 
-    format!(
-        r#"<munder>{}{}</munder>"#,
-        convert_to_mathml_impl(body, style_chain),
-        underbrace
-    )
+    let mut cells = vec![Vec::new()];
+    for node in finish_row(ctx, items) {
+        if is_cell_break(&node) {
+            cells.push(Vec::new());
+        } else {
+            cells
+                .last_mut()
+                .expect("There is always a current cell.")
+                .push(node);
+        }
+    }
+    cells
 }
 
-fn process_overbrace(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<OverbraceElem>()
-        .expect("Type conversion to `OverbraceElem` must be successful.");
+/// Whether `node` is a break between the cells of a row, see [`finish_cells`].
+fn is_cell_break(node: &Node) -> bool {
+    node.is("malignmark")
+}
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+/// The width of a space in the source, the width of a space of Typst's default
+/// math font.
+const SPACE_WIDTH: &str = "0.333em";
+
+/// Keep the spaces of the source only where Typst renders them: between two
+/// atoms, one of them spaced, e.g. text, that their classes do not space already.
+fn keep_spaces<'a>(ctx: &Context, row: Vec<(&'a Content, Node)>) -> Vec<(&'a Content, Node)> {
+    let script = ctx.size.get() <= MathSize::Script;
+    let mut res: Vec<(&Content, Node)> = Vec::with_capacity(row.len());
+    let mut space = None;
+
+    for (content, node) in row {
+        // Breaks between cells do not separate the atoms around them.
+        if is_cell_break(&node) {
+            res.push((content, node));
+            continue;
+        }
+        let last = res.iter().rev().find(|(_, last)| !is_cell_break(last));
 
-    let overbrace = if let Some(annotation) = annotation {
-        format!(
-            r#"<mover><mo stretchy="true">&#x23DE;</mo><mtext>{}</mtext></mover>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x23DE;</mo>"#.to_string()
-    };
+        if content.is::<SpaceElem>() {
+            // Spaces at the start of a row or after explicit spacing are dropped.
+            if last.is_some_and(|(last_content, last)| atom_class(last_content, last).is_some()) {
+                space = Some((content, node));
+            }
+            continue;
+        }
+
+        if let (Some(space), Some((last_content, last))) = (space.take(), last) {
+            let spaced = is_spaced(last_content, last) || is_spaced(content, &node);
+            let classes = atom_class(last_content, last).zip(atom_class(content, &node));
+            if spaced && classes.is_some_and(|(l, r)| class_rule(l, r, script).is_none()) {
+                res.push(space);
+            }
+        }
+        res.push((content, node));
+    }
 
-    format!(
-        r#"<mover>{}{}</mover>"#,
-        convert_to_mathml_impl(body, style_chain),
-        overbrace
-    )
+    res
 }
 
-fn process_underbracket(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<UnderbracketElem>()
-        .expect("Type conversion to `UnderbracketElem` must be successful.");
+/// Whether Typst puts the spaces of the source around `content`: text of several
+/// characters, boxes and fences such as `|`.
+fn is_spaced(content: &Content, node: &Node) -> bool {
+    if node_class(node) == Some(MathClass::Fence) {
+        return true;
+    }
+    let content = unstyled(content);
+    match content.to_packed::<TextElem>() {
+        Some(text) => {
+            let text = text.text();
+            text.chars().nth(1).is_some() && !text.chars().all(|c| c.is_ascii_digit() || c == '.')
+        }
+        None => content.is::<BoxElem>(),
+    }
+}
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+/// Resolve the form of binary and variable operators such as `-` from their
+/// neighbors, like Typst does: they are binary after an operand and unary at the
+/// start of a row or after an opening delimiter, a relation or another operator.
+fn resolve_forms(row: &mut [(&Content, Node)]) {
+    let mut last = None;
 
-    let underbracket = if let Some(annotation) = annotation {
-        format!(
-            r#"<munder><mo stretchy="true">&#x23B5;</mo><mtext>{}</mtext></munder>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
+    for idx in 0..row.len() {
+        let (content, node) = &row[idx];
+        let Some(class) = atom_class(content, node) else {
+            continue;
+        };
+        let after_operand = matches!(
+            last,
+            Some(MathClass::Normal | MathClass::Alphabetic | MathClass::Closing | MathClass::Fence)
+        );
+        let class = match class {
+            MathClass::Vary | MathClass::Binary if after_operand => MathClass::Binary,
+            MathClass::Vary | MathClass::Binary => MathClass::Unary,
+            class => class,
+        };
+
+        let form = match class {
+            // Nothing follows, e.g. `(a +)`.
+            MathClass::Binary if !has_operand(&row[idx + 1..]) => Some("postfix"),
+            MathClass::Binary => Some("infix"),
+            MathClass::Unary => Some("prefix"),
+            _ => None,
+        };
+        if let Some(form) = form {
+            if let Some(mo) = row[idx]
+                .1
+                .as_element_mut()
+                .filter(|element| element.name == "mo")
+            {
+                mo.set_attr("form", form);
+            }
+        }
+
+        last = Some(class);
+    }
+}
+
+/// Whether an operand follows in the `rest` of a row.
+fn has_operand(rest: &[(&Content, Node)]) -> bool {
+    match rest
+        .iter()
+        .find_map(|(content, node)| atom_class(content, node))
+    {
+        Some(class) => !matches!(
+            class,
+            MathClass::Closing | MathClass::Relation | MathClass::Punctuation
+        ),
+        None => false,
+    }
+}
+
+/// The math class of an atom of a row, like [`node_class`]. Named operators such as
+/// `sin`, which are converted to identifiers, are large operators like in Typst.
+fn atom_class(content: &Content, node: &Node) -> Option<MathClass> {
+    if is_function(content) {
+        Some(MathClass::Large)
     } else {
-        r#"<mo stretchy="true">&#x23B5;</mo>"#.to_string()
+        node_class(node)
+    }
+}
+
+/// Whether `node` is the invisible function application after a function name,
+/// see [`insert_invisible_operators`].
+fn is_function_application(node: &Node) -> bool {
+    node.as_element()
+        .is_some_and(|element| element.name == "mo" && element.text_content() == "\u{2061}")
+}
+
+/// The math class of a converted node, `None` for spacing and invisible operators,
+/// which are ignored when resolving forms.
+fn node_class(node: &Node) -> Option<MathClass> {
+    let Some(element) = node.as_element() else {
+        return Some(MathClass::Normal);
+    };
+
+    let class = match element.name.as_str() {
+        "mspace" => return None,
+        // Breaks between cells and invisible operators are not rendered.
+        "malignmark" => return None,
+        "mo" if matches!(element.text_content().as_str(), "\u{2061}" | "\u{2062}") => return None,
+        // An operator with attachments keeps the class of the operator.
+        _ => core_operator(node).map_or(MathClass::Normal, operator_class),
     };
 
-    format!(
-        r#"<munder>{}{}</munder>"#,
-        convert_to_mathml_impl(body, style_chain),
-        underbracket
-    )
+    Some(class)
 }
 
-fn process_overbracket(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<OverbracketElem>()
-        .expect("Type conversion to `OverbracketElem` must be successful.");
+/// The core operator of an embellished operator, e.g. the `mo` of `sum_i`, whose
+/// spacing renderers put around the whole embellished operator.
+fn core_operator(node: &Node) -> Option<&Element> {
+    let element = node.as_element()?;
+    match element.name.as_str() {
+        "mo" => Some(element),
+        "msub" | "msup" | "msubsup" | "munder" | "mover" | "munderover" | "mmultiscripts" => {
+            core_operator(element.children.first()?)
+        }
+        _ => None,
+    }
+}
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+/// The core operator of an embellished operator, see [`core_operator`].
+fn core_operator_mut(node: &mut Node) -> Option<&mut Element> {
+    let element = node.as_element_mut()?;
+    match element.name.as_str() {
+        "mo" => Some(element),
+        "msub" | "msup" | "msubsup" | "munder" | "mover" | "munderover" | "mmultiscripts" => {
+            core_operator_mut(element.children.first_mut()?)
+        }
+        _ => None,
+    }
+}
 
-    let overbracket = if let Some(annotation) = annotation {
-        format!(
-            r#"<mover><mo stretchy="true">&#x23B4;</mo><mtext>{}</mtext></mover>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x23B4;</mo>"#.to_string()
+fn operator_class(mo: &Element) -> MathClass {
+    let fence = mo.attr("fence") == Some("true");
+    match mo.attr("form") {
+        Some("prefix") if fence => MathClass::Opening,
+        Some("postfix") if fence => MathClass::Closing,
+        Some("infix") if fence => MathClass::Fence,
+        _ if mo.attr("largeop") == Some("true") => MathClass::Large,
+        form => {
+            let class = mo
+                .text_content()
+                .chars()
+                .next()
+                .and_then(unicode_math_class::class)
+                .unwrap_or(MathClass::Normal);
+            // The form of binary and variable operators is resolved already.
+            match (class, form) {
+                (MathClass::Vary | MathClass::Binary, Some("prefix")) => MathClass::Unary,
+                (MathClass::Vary | MathClass::Binary, Some(_)) => MathClass::Binary,
+                (class, _) => class,
+            }
+        }
+    }
+}
+
+/// Thin spacing of Typst, in em.
+const THIN: f64 = 1.0 / 6.0;
+
+/// Medium spacing of Typst, in em.
+const MEDIUM: f64 = 2.0 / 9.0;
+
+/// Thick spacing of Typst, in em.
+const THICK: f64 = 5.0 / 18.0;
+
+/// The spacing Typst puts between atoms of class `l` and `r`, in em.
+fn class_spacing(l: MathClass, r: MathClass, script: bool) -> Option<f64> {
+    class_rule(l, r, script).flatten()
+}
+
+/// The rule of Typst for the spacing between atoms of class `l` and `r`, `None` if
+/// there is none, in which case a space of the source between them is kept.
+fn class_rule(l: MathClass, r: MathClass, script: bool) -> Option<Option<f64>> {
+    use MathClass::*;
+
+    let spacing = match (l, r) {
+        // No spacing before punctuation, thin spacing after punctuation, unless in
+        // script size.
+        (_, Punctuation) => None,
+        (Punctuation, _) if !script => Some(THIN),
+        // No spacing after opening delimiters and before closing delimiters.
+        (Opening, _) | (_, Closing) => None,
+        // Thick spacing around relations, unless followed by another relation or in
+        // script size.
+        (Relation, Relation) => None,
+        (Relation, _) | (_, Relation) if !script => Some(THICK),
+        // Medium spacing around binary operators, unless in script size.
+        (Binary, _) | (_, Binary) if !script => Some(MEDIUM),
+        // Thin spacing around large operators, unless to the left of an opening
+        // delimiter.
+        (Large, Opening | Fence) => None,
+        (Large, _) | (_, Large) => Some(THIN),
+        _ => return None,
     };
 
-    format!(
-        r#"<mover>{}{}</mover>"#,
-        convert_to_mathml_impl(body, style_chain),
-        overbracket
-    )
+    Some(spacing)
 }
 
-fn process_underparen(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<UnderparenElem>()
-        .expect("Type conversion to `UnderparenElem` must be successful.");
+/// Replace the spacing renderers take from their operator dictionary with the
+/// spacing Typst puts between the classes of neighboring atoms in `row`.
+fn apply_spacing(ctx: &Context, row: &mut Vec<Node>) {
+    let script = ctx.size.get() <= MathSize::Script;
+    let mut spaces = Vec::new();
+    let mut last: Option<(usize, MathClass)> = None;
+
+    for idx in 0..row.len() {
+        if let Some(mo) = core_operator_mut(&mut row[idx]) {
+            mo.set_attr("lspace", "0em");
+            mo.set_attr("rspace", "0em");
+        }
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+        // Explicit spacing replaces the spacing between classes.
+        if row[idx].is("mspace") {
+            last = None;
+            continue;
+        }
+        let Some(class) = node_class(&row[idx]) else {
+            continue;
+        };
+        // Function names are followed by the function application.
+        let class = if row.get(idx + 1).is_some_and(is_function_application) {
+            MathClass::Large
+        } else {
+            class
+        };
 
-    let underparen = if let Some(annotation) = annotation {
-        format!(
-            r#"<munder><mo stretchy="true">&#x2323;</mo><mtext>{}</mtext></munder>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x2323;</mo>"#.to_string()
-    };
+        if let Some((l, l_class)) = last {
+            // Like in Typst, the delimiters of a group are atoms of the row.
+            let r_class = opening_class(&row[idx]).unwrap_or(class);
+            if let Some(space) = class_spacing(l_class, r_class, script) {
+                let width = format!("{}em", length::number(space));
+                if let Some(mo) = core_operator_mut(&mut row[idx]) {
+                    mo.set_attr("lspace", width);
+                } else if let Some(mo) = core_operator_mut(&mut row[l]) {
+                    mo.set_attr("rspace", width);
+                } else {
+                    spaces.push((idx, width));
+                }
+            }
+        }
+        last = Some((idx, class));
+    }
 
-    // FIXME: Both Firefox and Chrome does not render properly. However, I do not have
-    // any idea how to implement it using other methods.
-    // MathJax processes it correctly.
-    format!(
-        r#"<munder>{}{}</munder>"#,
-        convert_to_mathml_impl(body, style_chain),
-        underparen
-    )
+    for (idx, width) in spaces.into_iter().rev() {
+        row.insert(idx, Element::new("mspace").with_attr("width", width).into());
+    }
 }
 
-fn process_overparen(content: &Content, style_chain: StyleChain) -> String {
-    let coerced = content
-        .to_packed::<OverparenElem>()
-        .expect("Type conversion to `OverparenElem` must be successful.");
+/// The class of the opening delimiter of a group in delimiters, e.g. `(x)`.
+fn opening_class(node: &Node) -> Option<MathClass> {
+    let element = node.as_element().filter(|element| element.name == "mrow")?;
+    let first = element.children.first()?.as_element()?;
+    (first.name == "mo" && first.attr("fence") == Some("true")).then(|| operator_class(first))
+}
 
-    let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
+/// Make implicit operations explicit for screen readers and other consumers of
+/// the semantics: function application after function names, e.g. `sin x`, and
+/// multiplication between factors, e.g. `2 x y`.
+fn insert_invisible_operators(row: Vec<(&Content, Node)>) -> Vec<Node> {
+    let mut res = Vec::with_capacity(row.len());
+    let mut row = row.into_iter();
+
+    while let Some((content, node)) = row.next() {
+        // Operators go before breaks between cells, which do not separate the atoms
+        // around them.
+        let next = row.as_slice().iter().find(|(_, next)| !is_cell_break(next));
+        let Some((next_content, next_node)) = next.filter(|_| !is_cell_break(&node)) else {
+            res.push(node);
+            continue;
+        };
 
-    let overparen = if let Some(annotation) = annotation {
-        format!(
-            r#"<mover><mo stretchy="true">&#x2322;</mo><mtext>{}</mtext></mover>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x2322;</mo>"#.to_string()
+        let operator = if is_function(content) {
+            // The argument may start with a prefix operator, e.g. `sin -x`.
+            let argument = !next_node.is("mo")
+                || next_node
+                    .as_element()
+                    .is_some_and(|mo| mo.attr("form") == Some("prefix"));
+            argument.then(|| {
+                let apply = Element::new("mo").with_text("\u{2061}");
+                // Renderers do not space the function application, put the thin
+                // space Typst puts after function names, unless delimiters follow,
+                // e.g. `sin(x)`.
+                if is_fenced(next_content) {
+                    apply
+                } else {
+                    apply.with_attr("rspace", format!("{}em", length::number(THIN)))
+                }
+            })
+        } else if is_factor(&node)
+            && is_factor(next_node)
+            && !is_function(next_content)
+            // `f(x)` may be a function application as well.
+            && !(node.is("mi") && is_fenced(next_content))
+        {
+            Some(Element::new("mo").with_text("\u{2062}"))
+        } else {
+            None
+        };
+
+        res.push(node);
+        if let Some(operator) = operator {
+            res.push(operator.into());
+        }
+    }
+
+    res
+}
+
+/// Whether `content` is a named operator, possibly with attachments, e.g. `lim_(x -> 0)`.
+fn is_function(content: &Content) -> bool {
+    let content = unstyled(content);
+    match content.to_packed::<AttachElem>() {
+        Some(attach) => unstyled(attach.base()).is::<OpElem>(),
+        None => content.is::<OpElem>(),
+    }
+}
+
+/// Whether `content` is wrapped in delimiters, e.g. `(x + 1)`.
+fn is_fenced(content: &Content) -> bool {
+    unstyled(content).is::<LrElem>()
+}
+
+/// Whether `node` is an operand of an implicit multiplication.
+fn is_factor(node: &Node) -> bool {
+    let Some(element) = node.as_element() else {
+        return false;
     };
 
-    // FIXME: Both Firefox and Chrome does not render properly. However, I do not have
-    // any idea how to implement it using other methods.
-    // MathJax processes it correctly.
-    format!(
-        r#"<mover>{}{}</mover>"#,
-        convert_to_mathml_impl(body, style_chain),
-        overparen
-    )
+    match element.name.as_str() {
+        "mi" | "mn" | "mfrac" | "msqrt" | "mroot" => true,
+        "msub" | "msup" | "msubsup" | "mmultiscripts" => {
+            element.children.first().is_some_and(is_factor)
+        }
+        // A group in delimiters.
+        "mrow" => element
+            .children
+            .first()
+            .and_then(Node::as_element)
+            .is_some_and(|first| {
+                first.attr("form") == Some("prefix") && first.attr("fence") == Some("true")
+            }),
+        _ => false,
+    }
+}
+
+/// `content` without the styles applied to it.
+fn unstyled(mut content: &Content) -> &Content {
+    while let Some(styled) = content.to_packed::<StyledElem>() {
+        content = styled.child();
+    }
+    content
 }
 
-fn process_undershell(content: &Content, style_chain: StyleChain) -> String {
+fn process_underline(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
-        .to_packed::<UndershellElem>()
-        .expect("Type conversion to `UndershellElem` must be successful.");
+        .to_packed::<UnderlineElem>()
+        .expect("Type conversion to `UnderlineElem` must be successful.");
 
     let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
 
-    let undershell = if let Some(annotation) = annotation {
-        format!(
-            r#"<munder><mo stretchy="true">&#x23E1;</mo><mtext>{}</mtext></munder>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
-        )
-    } else {
-        r#"<mo stretchy="true">&#x23E1;</mo>"#.to_string()
-    };
+    if ctx.options.profile == TargetProfile::Full {
+        return Element::new("munder")
+            .with_attr("accentunder", "true")
+            .with_child(convert_required(ctx, body, style_chain))
+            .with_child(
+                Element::new("mo")
+                    .with_attr("stretchy", "true")
+                    .with_text("\u{332}"),
+            )
+            .into();
+    }
 
-    // FIXME: Both Firefox, Chrome and MathJax does not render properly. However, I do not have
-    // any idea how to implement it using other methods.
-    format!(
-        r#"<munder>{}{}</munder>"#,
-        convert_to_mathml_impl(body, style_chain),
-        undershell
-    )
+    // FIXME: Now Chrome does not respect <munder> with underline strechy operater. I opened a
+    // bug report https://issues.chromium.org/issues/386610915. When the bug is resolved, this
+    // should switch to <munder>.
+    Element::new("mrow")
+        .with_attr(
+            "style",
+            "border-bottom: 1px solid currentColor; display: inline-block;",
+        )
+        .with_children(convert_to_mathml_impl(ctx, body, style_chain))
+        .into()
 }
 
-fn process_overshell(content: &Content, style_chain: StyleChain) -> String {
+fn process_overline(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
-        .to_packed::<OvershellElem>()
-        .expect("Type conversion to `OvershellElem` must be successful.");
+        .to_packed::<OverlineElem>()
+        .expect("Type conversion to `OverlineElem` must be successful.");
 
     let body = coerced.body();
-    let annotation = coerced.annotation(style_chain);
 
-    let overshell = if let Some(annotation) = annotation {
-        format!(
-            r#"<mover><mo stretchy="true">&#x23E0;</mo><mtext>{}</mtext></mover>"#,
-            convert_to_mathml_impl(&annotation, style_chain)
+    if ctx.options.profile == TargetProfile::Full {
+        return Element::new("mover")
+            .with_attr("accent", "true")
+            .with_child(convert_required(ctx, body, style_chain))
+            .with_child(
+                Element::new("mo")
+                    .with_attr("stretchy", "true")
+                    .with_text("\u{203E}"),
+            )
+            .into();
+    }
+
+    // FIXME: Now Chrome does not respect <mover> with overline strechy operater. I opened a
+    // bug report https://issues.chromium.org/issues/386610915. When the bug is resolved, this
+    // should switch to <mover>.
+    Element::new("mrow")
+        .with_attr(
+            "style",
+            "border-top: 1px solid currentColor; display: inline-block;",
         )
+        .with_children(convert_to_mathml_impl(ctx, body, style_chain))
+        .into()
+}
+
+/// A brace, bracket, paren or shell over or under its body with an optional
+/// annotation in script size beyond it, e.g. `underbrace(1 + 2, "sum")`.
+fn process_spreader(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
+    let (body, annotation, glyph, over) = if let Some(elem) = content.to_packed::<UnderbraceElem>()
+    {
+        (elem.body(), elem.annotation(style_chain), '\u{23DF}', false)
+    } else if let Some(elem) = content.to_packed::<OverbraceElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23DE}', true)
+    } else if let Some(elem) = content.to_packed::<UnderbracketElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23B5}', false)
+    } else if let Some(elem) = content.to_packed::<OverbracketElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23B4}', true)
+    } else if let Some(elem) = content.to_packed::<UnderparenElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23DD}', false)
+    } else if let Some(elem) = content.to_packed::<OverparenElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23DC}', true)
+    } else if let Some(elem) = content.to_packed::<UndershellElem>() {
+        (elem.body(), elem.annotation(style_chain), '\u{23E1}', false)
+    } else {
+        let elem = content
+            .to_packed::<OvershellElem>()
+            .expect("Type conversion to `OvershellElem` must be successful.");
+        (elem.body(), elem.annotation(style_chain), '\u{23E0}', true)
+    };
+    let (name, accent) = if over {
+        ("mover", "accent")
+    } else {
+        ("munder", "accentunder")
+    };
+
+    // Browsers stretch braces and brackets but not parens and shells, these are
+    // drawn around the body with the polyfill CSS instead.
+    let spread = if ctx.options.profile == TargetProfile::Core
+        && matches!(glyph, '\u{23DC}'..='\u{23DD}' | '\u{23E0}'..='\u{23E1}')
+    {
+        Element::new("mrow")
+            .with_attr(
+                "class",
+                format!("{}-{}", ctx.options.class_prefix, content.elem().name()),
+            )
+            .with_children(convert_to_mathml_impl(ctx, body, style_chain))
     } else {
-        r#"<mo stretchy="true">&#x23E0;</mo>"#.to_string()
+        // The glyph hugs the body like an accent.
+        Element::new(name)
+            .with_attr(accent, "true")
+            .with_child(convert_required(ctx, body, style_chain))
+            .with_child(
+                Element::new("mo")
+                    .with_attr("stretchy", "true")
+                    .with_text(glyph.to_string()),
+            )
     };
 
-    // FIXME: Both Firefox, Chrome and MathJax does not render properly. However, I do not have
-    // any idea how to implement it using other methods.
-    format!(
-        r#"<mover>{}{}</mover>"#,
-        convert_to_mathml_impl(body, style_chain),
-        overshell
-    )
+    match annotation {
+        Some(annotation) => Element::new(name)
+            .with_child(spread)
+            .with_child(ctx.at_size(ctx.script_size(), || {
+                convert_required(ctx, &annotation, style_chain)
+            }))
+            .into(),
+        None => spread.into(),
+    }
 }
 
-fn process_mid(content: &Content, style_chain: StyleChain) -> String {
+fn process_mid(ctx: &Context, content: &Content, style_chain: StyleChain) -> Node {
     let coerced = content
         .to_packed::<MidElem>()
         .expect("Type conversion to `OvershellElem` must be successful.");
 
     let body = coerced.body();
-    let text = if body.elem().name() == "text" {
-        body.plain_text().to_string()
+    let mo = Element::new("mo")
+        .with_attr("fence", "true")
+        .with_attr("form", "infix")
+        .with_attr("stretchy", "true")
+        .with_attr("symmetric", "true");
+
+    if body.elem().name() == "text" {
+        mo.with_text(body.plain_text()).into()
     } else {
-        convert_to_mathml_impl(body, style_chain)
+        mo.with_children(convert_to_mathml_impl(ctx, body, style_chain))
+            .into()
+    }
+}
+
+/// Wrap `body` in the token element matching `class`.
+///
+/// `text` is the unstyled text of `body`, it decides whether a single character
+/// identifier needs `mathvariant="normal"`.
+fn math_class_helper(body: Node, text: &str, class: &MathClass, style_chain: StyleChain) -> Node {
+    let is_italic = EquationElem::italic_in(style_chain);
+
+    let is_normal = matches!(is_italic, Smart::Custom(false)) || {
+        let char_vec: Vec<_> = text.chars().collect();
+        char_vec.len() == 1 && style::italic_exception::is_normal(char_vec[0])
+    };
+
+    let operator = |attrs: &[(&str, &str)]| {
+        let mut mo = Element::new("mo");
+        for (name, value) in attrs {
+            mo.set_attr(*name, *value);
+        }
+        mo.with_child(body.clone()).into()
+    };
+
+    match class {
+        MathClass::Normal | MathClass::Alphabetic | MathClass::Diacritic => {
+            let mut mi = Element::new("mi");
+            if is_normal {
+                mi.set_attr("mathvariant", "normal");
+            }
+            mi.with_child(body).into()
+        }
+        MathClass::Binary => operator(&[("form", "infix")]),
+        MathClass::Closing => operator(&[("fence", "true"), ("form", "postfix")]),
+        MathClass::Fence => operator(&[("fence", "true")]),
+        MathClass::GlyphPart => operator(&[]),
+        MathClass::Large => operator(&[("largeop", "true"), ("symmetric", "true")]),
+        MathClass::Opening => operator(&[("fence", "true"), ("form", "prefix")]),
+        MathClass::Punctuation => operator(&[("separator", "true")]),
+        MathClass::Relation => operator(&[]),
+        MathClass::Space => Element::new("mspace").with_attr("width", "0.333em").into(),
+        MathClass::Unary => operator(&[("form", "prefix")]),
+        // The form depends on the neighbors, see `resolve_forms`.
+        MathClass::Vary => operator(&[]),
+        MathClass::Special => operator(&[]),
+    }
+}
+
+/// An `<mi>`, upright if `is_normal` is set.
+fn identifier(text: String, is_normal: bool) -> Node {
+    let mut mi = Element::new("mi");
+    if is_normal {
+        mi.set_attr("mathvariant", "normal");
+    }
+    mi.with_text(text).into()
+}
+
+/// A table cell with the given horizontal alignment.
+fn mtd(ctx: &Context, align: FixedAlignment, children: impl IntoIterator<Item = Node>) -> Element {
+    let align = match align {
+        FixedAlignment::Start => "left",
+        FixedAlignment::Center => "center",
+        FixedAlignment::End => "right",
     };
 
-    format!(
-        r#"<mo fence="true" form="infix" stretchy="true">{}</mo>"#,
-        text
-    )
+    // MathML Core leaves out `columnalign`, browsers that implement only Core align
+    // the cell with CSS instead.
+    let mut cell = Element::new("mtd").with_attr("columnalign", align);
+    if ctx.options.profile == TargetProfile::Core {
+        push_style(&mut cell, &format!("text-align: {}", align));
+    }
+
+    cell.with_children(children)
 }
 
-fn math_class_helper(
-    text: &str,
-    original_text: Option<&str>,
-    class: &MathClass,
+/// Typst's default gap between the rows of `mat`, `vec` and `cases`, in em.
+const DEFAULT_ROW_GAP: f64 = 0.2;
+
+/// Typst's default gap between the columns of `mat`, in em.
+const DEFAULT_COLUMN_GAP: f64 = 0.5;
+
+/// Set the gaps between the rows and columns of `table`, unless they are Typst's
+/// defaults.
+fn table_spacing(
+    ctx: &Context,
+    table: &mut Element,
+    row_gap: Rel<Abs>,
+    column_gap: Option<Rel<Abs>>,
     style_chain: StyleChain,
-) -> String {
-    let is_italic = EquationElem::italic_in(style_chain);
+) {
+    let row_gap = Some(row_gap).filter(|gap| !length::is_em(*gap, DEFAULT_ROW_GAP, style_chain));
+    let column_gap = column_gap.filter(|gap| !length::is_em(*gap, DEFAULT_COLUMN_GAP, style_chain));
 
-    let identifier_attr = if matches!(is_italic, Smart::Custom(false)) {
-        r#" mathvariant="normal""#
-    } else {
-        let text = if let Some(original_text) = original_text {
-            original_text
-        } else {
-            text
+    if ctx.options.profile == TargetProfile::Full {
+        if let Some(gap) = row_gap {
+            table.set_attr("rowspacing", length::rel(gap, style_chain));
+        }
+        if let Some(gap) = column_gap {
+            table.set_attr("columnspacing", length::rel(gap, style_chain));
+        }
+        return;
+    }
+
+    // MathML Core ignores `rowspacing` and `columnspacing`, each cell gets half of
+    // the gap on both sides instead.
+    let mut declarations = Vec::new();
+    if let Some(gap) = row_gap {
+        declarations.push(format!(
+            "padding-block: {}",
+            length::rel(length::half(gap), style_chain)
+        ));
+    }
+    if let Some(gap) = column_gap {
+        declarations.push(format!(
+            "padding-inline: {}",
+            length::rel(length::half(gap), style_chain)
+        ));
+    }
+    for declaration in declarations {
+        for (_, _, cell) in cells_mut(table) {
+            push_style(cell, &declaration);
+        }
+    }
+}
+
+/// Draw the augmentation lines of a matrix with `rows` rows and columns split into
+/// `groups` of table columns.
+fn table_augment(
+    ctx: &Context,
+    table: &mut Element,
+    augment: &Augment<Abs>,
+    rows: usize,
+    groups: &[usize],
+    style_chain: StyleChain,
+    span: Span,
+) {
+    // A line at `k` is drawn after the `k`-th row or column, negative offsets count
+    // from the end. Like Typst, lines at the edges of the matrix are rejected.
+    let offsets = |offsets: &AugmentOffsets, count: usize, line: &str, of: &str| {
+        let mut res = Vec::new();
+        for &offset in &offsets.0 {
+            if offset == 0 || offset.unsigned_abs() >= count {
+                // Like Typst, the message has the offset counted from the start.
+                let resolved = if offset < 0 {
+                    count as isize + offset
+                } else {
+                    offset
+                };
+                let message = format!(
+                    "cannot draw a {} line after {} {} of a matrix with {} {}s",
+                    line, of, resolved, count, of
+                );
+                let error = SourceDiagnostic::error(span, message);
+                ctx.fail(ConvertError::Compile(vec![Diagnostic::from_typst(
+                    &error,
+                    |span| ctx.world.locate(span),
+                )]));
+                continue;
+            }
+            res.push(if offset < 0 {
+                count - offset.unsigned_abs()
+            } else {
+                offset.unsigned_abs()
+            });
+        }
+        res
+    };
+    let hlines = offsets(&augment.hline, rows, "horizontal", "row");
+    // The columns of the matrix may be split at alignment points into groups of
+    // several columns of the table.
+    let vlines: Vec<usize> = offsets(&augment.vline, groups.len(), "vertical", "column")
+        .into_iter()
+        .map(|offset| groups[..offset].iter().sum())
+        .collect();
+    let columns = groups.iter().sum();
+
+    let stroke = match &augment.stroke {
+        Smart::Custom(stroke) => stroke.clone(),
+        Smart::Auto => Stroke::default(),
+    };
+    let line_style = line_style(&stroke);
+
+    if ctx.options.profile == TargetProfile::Full {
+        // Only lines between rows or columns can be expressed in MathML.
+        let lines = |lines: &[usize], count: usize| {
+            (1..count)
+                .map(|idx| {
+                    if lines.contains(&idx) {
+                        line_style
+                    } else {
+                        "none"
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
         };
-        let char_vec: Vec<_> = text.chars().collect();
-        if char_vec.len() == 1 && style::italic_exception::is_normal(char_vec[0]) {
-            r#" mathvariant="normal""#
-        } else {
-            ""
+        if !hlines.is_empty() {
+            table.set_attr("rowlines", lines(&hlines, rows));
+        }
+        if !vlines.is_empty() {
+            table.set_attr("columnlines", lines(&vlines, columns));
         }
+        return;
+    }
+
+    let thickness = match stroke.thickness {
+        Smart::Custom(thickness) => length::em(thickness, style_chain),
+        Smart::Auto => "0.05em".to_string(),
+    };
+    let color = match stroke.paint {
+        Smart::Custom(Paint::Solid(color)) => color.to_hex().to_string(),
+        _ => "currentColor".to_string(),
     };
+    let border = format!("{} {} {}", thickness, line_style, color);
 
-    let text = escape_helper(text);
-    match class {
-        MathClass::Normal => format!("<mi{}>{}</mi>", identifier_attr, text),
-        MathClass::Alphabetic => format!("<mi{}>{}</mi>", identifier_attr, text),
-        MathClass::Binary => format!(r#"<mo form="infix">{}</mo>"#, text),
-        MathClass::Closing => format!(r#"<mo fence="true" form="postfix">{}</mo>"#, text),
-        MathClass::Diacritic => format!("<mi{}>{}</mi>", identifier_attr, text),
-        MathClass::Fence => format!(r#"<mo fence="true">{}</mo>"#, text),
-        MathClass::GlyphPart => format!("<mo>{}</mo>", text),
-        MathClass::Large => format!(r#"<mo largeop="true">{}</mo>"#, text),
-        MathClass::Opening => format!(r#"<mo fence="true" form="prefix">{}</mo>"#, text),
-        MathClass::Punctuation => format!(r#"<mo separator="true">{}</mo>"#, text),
-        MathClass::Relation => format!("<mo>{}</mo>", text),
-        MathClass::Space => format!(r#"<mspace width="0.333em"></mspace>"#),
-        MathClass::Unary => format!(r#"<mo form="prefix">{}</mo>"#, text),
-        // FIXME: need further processing
-        MathClass::Vary => format!("<mo>{}</mo>", text),
-        MathClass::Special => format!("<mo>{}</mo>", text),
-    }
-}
-
-fn escape_helper(text: &str) -> String {
-    // See <https://html.spec.whatwg.org/multipage/syntax.html#syntax-charref>
-    let mut res = String::new();
-    for c in text.chars() {
-        match c {
-            '&' => res.push_str("&amp;"),
-            '<' => res.push_str("&lt;"),
-            '>' => res.push_str("&gt;"),
-            '"' => res.push_str("&quot;"),
-            '\'' => res.push_str("&apos;"),
-            // c if charsets::is_w3c_text_char(c) && c != '\r' => {
-            //     res.push_str(&format!("&#x{:x};", c as u32))
-            // }
-            _ => res.push(c),
+    for (row, column, cell) in cells_mut(table) {
+        if hlines.contains(&(row + 1)) {
+            push_style(cell, &format!("border-bottom: {}", border));
+        }
+        if vlines.contains(&(column + 1)) {
+            push_style(cell, &format!("border-right: {}", border));
         }
     }
+}
 
-    res
+/// The CSS line style of `stroke`: `dotted` if its dashes are dots, i.e. as long as
+/// the line is thick, `dashed` for other dash patterns.
+fn line_style(stroke: &Stroke<Abs>) -> &'static str {
+    let Smart::Custom(Some(pattern)) = &stroke.dash else {
+        return "solid";
+    };
+    if pattern.array.is_empty() {
+        return "solid";
+    }
+
+    let thickness = stroke.thickness.unwrap_or(Abs::pt(1.0));
+    let dotted = pattern.array.iter().step_by(2).all(|dash| match dash {
+        DashLength::LineWidth => true,
+        DashLength::Length(length) => *length <= thickness,
+    });
+    if dotted {
+        "dotted"
+    } else {
+        "dashed"
+    }
+}
+
+/// The cells of `table` with their row and column.
+fn cells_mut(table: &mut Element) -> impl Iterator<Item = (usize, usize, &mut Element)> {
+    table
+        .children
+        .iter_mut()
+        .filter_map(Node::as_element_mut)
+        .enumerate()
+        .flat_map(|(row, tr)| {
+            tr.children
+                .iter_mut()
+                .filter_map(Node::as_element_mut)
+                .enumerate()
+                .map(move |(column, cell)| (row, column, cell))
+        })
+}
+
+/// Append a CSS declaration to the `style` of `element`.
+fn push_style(element: &mut Element, declaration: &str) {
+    let style = match element.attr("style") {
+        Some(style) => format!("{}; {}", style, declaration),
+        None => declaration.to_string(),
+    };
+    element.set_attr("style", style);
 }
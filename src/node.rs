@@ -0,0 +1,282 @@
+//! A minimal MathML document model.
+//!
+//! The converter builds a tree of [`Node`]s, which can be inspected or rewritten
+//! with a [`Visitor`] or [`VisitorMut`] before it is serialized with
+//! [`Node::write_to`] or [`ToString`].
+
+use std::fmt::{self, Display, Formatter, Write};
+use std::io;
+
+/// A MathML node, either an element or character data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum Node {
+    Element(Element),
+    /// Unescaped text, it is escaped when serialized.
+    Text(String),
+}
+
+/// A MathML element with its attributes in insertion order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Set the attribute `name`, replacing a previous value.
+    pub fn with_attr(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_attr(name, value);
+        self
+    }
+
+    pub fn with_child(mut self, child: impl Into<Node>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    pub fn with_children(mut self, children: impl IntoIterator<Item = Node>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    pub fn with_text(self, text: impl Into<String>) -> Self {
+        self.with_child(Node::Text(text.into()))
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the attribute `name`, replacing a previous value.
+    pub fn set_attr(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.attrs.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, old)) => *old = value,
+            None => self.attrs.push((name, value)),
+        }
+    }
+
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        let idx = self.attrs.iter().position(|(key, _)| key == name)?;
+        Some(self.attrs.remove(idx).1)
+    }
+
+    /// The concatenated text of all descendants.
+    pub fn text_content(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            child.push_text_content(&mut text);
+        }
+        text
+    }
+
+    /// Serialize the element as MathML into `w`.
+    pub fn write_to(&self, w: &mut impl Write) -> fmt::Result {
+        write!(w, "<{}", self.name)?;
+        for (name, value) in &self.attrs {
+            write!(w, r#" {}=""#, name)?;
+            write_escaped(w, value)?;
+            w.write_char('"')?;
+        }
+        w.write_char('>')?;
+        for child in &self.children {
+            child.write_to(w)?;
+        }
+        write!(w, "</{}>", self.name)
+    }
+
+    /// Serialize the element as MathML into the byte sink `w`, e.g. a file or a socket.
+    pub fn write_io(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: w,
+            error: None,
+        };
+        match self.write_to(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+impl Node {
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            Self::Text(_) => None,
+        }
+    }
+
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match self {
+            Self::Element(element) => Some(element),
+            Self::Text(_) => None,
+        }
+    }
+
+    /// Whether this is an element named `name`.
+    pub fn is(&self, name: &str) -> bool {
+        self.as_element()
+            .is_some_and(|element| element.name == name)
+    }
+
+    /// The concatenated text of this node and all of its descendants.
+    pub fn text_content(&self) -> String {
+        let mut text = String::new();
+        self.push_text_content(&mut text);
+        text
+    }
+
+    fn push_text_content(&self, buf: &mut String) {
+        match self {
+            Self::Element(element) => {
+                for child in &element.children {
+                    child.push_text_content(buf);
+                }
+            }
+            Self::Text(text) => buf.push_str(text),
+        }
+    }
+
+    /// Serialize the node as MathML into `w`.
+    pub fn write_to(&self, w: &mut impl Write) -> fmt::Result {
+        match self {
+            Self::Element(element) => element.write_to(w),
+            Self::Text(text) => write_escaped(w, text),
+        }
+    }
+
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        match self {
+            Self::Element(element) => visitor.visit_element(element),
+            Self::Text(text) => visitor.visit_text(text),
+        }
+    }
+
+    pub fn accept_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        match self {
+            Self::Element(element) => visitor.visit_element_mut(element),
+            Self::Text(text) => visitor.visit_text_mut(text),
+        }
+    }
+}
+
+impl From<Element> for Node {
+    fn from(element: Element) -> Self {
+        Self::Element(element)
+    }
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_to(f)
+    }
+}
+
+/// Read-only traversal of a MathML tree.
+///
+/// The default methods visit every node in document order, override
+/// [`Visitor::visit_element`] and call [`walk_element`] to keep descending.
+pub trait Visitor {
+    fn visit_element(&mut self, element: &Element) {
+        walk_element(self, element);
+    }
+
+    fn visit_text(&mut self, _text: &str) {}
+}
+
+/// Visit all children of `element`.
+pub fn walk_element<V: Visitor + ?Sized>(visitor: &mut V, element: &Element) {
+    for child in &element.children {
+        child.accept(visitor);
+    }
+}
+
+/// Traversal of a MathML tree that may rewrite it in place, e.g. to rename
+/// elements, add attributes or replace children.
+///
+/// The default methods visit every node in document order, override
+/// [`VisitorMut::visit_element_mut`] and call [`walk_element_mut`] to keep
+/// descending.
+pub trait VisitorMut {
+    fn visit_element_mut(&mut self, element: &mut Element) {
+        walk_element_mut(self, element);
+    }
+
+    fn visit_text_mut(&mut self, _text: &mut String) {}
+}
+
+/// Visit all children of `element` mutably.
+pub fn walk_element_mut<V: VisitorMut + ?Sized>(visitor: &mut V, element: &mut Element) {
+    for child in &mut element.children {
+        child.accept_mut(visitor);
+    }
+}
+
+/// Forwards UTF-8 to an [`io::Write`], keeping the error `fmt::Write` can not carry.
+struct IoAdapter<'a, W: io::Write> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+fn write_escaped(w: &mut impl Write, text: &str) -> fmt::Result {
+    // Runs of characters that need no escaping are written at once.
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        // See <https://html.spec.whatwg.org/multipage/syntax.html#syntax-charref>
+        let escaped = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\'' => "&apos;",
+            // Invisible characters are easy to lose when the output is copied or
+            // inspected, so they are written as character references, as are line
+            // breaks, e.g. of an annotation, which keeps each element on one line.
+            '\n' | '\r' | '\u{A0}' | '\u{200B}'..='\u{200D}' | '\u{2060}'..='\u{2064}' => "",
+            _ => continue,
+        };
+
+        w.write_str(&text[start..idx])?;
+        if escaped.is_empty() {
+            write!(w, "&#x{:X};", c as u32)?;
+        } else {
+            w.write_str(escaped)?;
+        }
+        start = idx + c.len_utf8();
+    }
+
+    w.write_str(&text[start..])
+}
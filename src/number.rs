@@ -0,0 +1,148 @@
+//! Recognition of numbers, which Typst splits at separators, e.g. `1,000` into
+//! `1`, `,` and `000`.
+
+use typst::foundations::Content;
+use typst::text::{SpaceElem, TextElem};
+
+use crate::converter::DecimalSeparator;
+
+impl DecimalSeparator {
+    fn decimal(self) -> char {
+        match self {
+            Self::Dot => '.',
+            Self::Comma => ',',
+        }
+    }
+
+    /// The separator of digit groups, besides spaces.
+    fn group(self) -> char {
+        match self {
+            Self::Dot => ',',
+            Self::Comma => '.',
+        }
+    }
+}
+
+/// Whether `text` is a number: digits in any script, separated by single dots or
+/// commas, and possibly ending in a vulgar fraction, e.g. `1.5`, `٣` or `1½`.
+pub(crate) fn is_number(text: &str) -> bool {
+    let text = match text.strip_suffix(is_fraction) {
+        Some("") => return true,
+        Some(rest) => rest,
+        None => text,
+    };
+    text.split(['.', ',']).all(is_digits)
+}
+
+/// Merge the number starting at the first of `children` with the separators,
+/// digit groups, fraction and exponent following it, as many children as they
+/// span. `None` if the number is a single child.
+pub(crate) fn merge(children: &[Content], separator: DecimalSeparator) -> Option<(usize, String)> {
+    let text = |idx: usize| {
+        children
+            .get(idx)
+            .and_then(|child| child.to_packed::<TextElem>())
+            .map(|text| text.text().as_str())
+    };
+
+    let mut number = text(0).filter(|text| is_number(text))?.to_string();
+    let mut decimal = number.contains(separator.decimal());
+    let mut grouped = number.contains(separator.group());
+    let mut len = 1;
+
+    // Digit groups and the decimal part, e.g. `1,000.5` or `3.141 592`.
+    loop {
+        let sep = match children.get(len) {
+            Some(child) if child.is::<SpaceElem>() => ' ',
+            _ => match text(len).map(|text| text.chars()) {
+                Some(mut chars) => match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => break,
+                },
+                None => break,
+            },
+        };
+        let Some(digits) = text(len + 1).filter(|text| is_digits(text)) else {
+            break;
+        };
+
+        let group = digits.chars().count() == 3;
+        if sep == separator.decimal() && !decimal {
+            decimal = true;
+            number.push(sep);
+        } else if sep == separator.group() && !decimal && group {
+            grouped = true;
+            number.push(sep);
+        } else if sep == ' ' && (decimal || grouped) && group {
+            // Spaces only separate the digit groups of numbers with a separator, as
+            // `2 345` may as well be two numbers. A narrow no-break space keeps the
+            // groups together.
+            number.push('\u{202F}');
+        } else {
+            break;
+        }
+        number.push_str(digits);
+        len += 2;
+    }
+
+    // A vulgar fraction, e.g. `1½`.
+    if let Some(fraction) = text(len).filter(|text| text.chars().all(is_fraction)) {
+        if !decimal && fraction.chars().count() == 1 {
+            number.push_str(fraction);
+            len += 1;
+        }
+    }
+
+    // An exponent, e.g. `3.14e-5`.
+    if let Some(e) = text(len).filter(|text| matches!(*text, "e" | "E")) {
+        let sign = text(len + 1).filter(|text| matches!(*text, "+" | "-" | "−"));
+        let at = len + 1 + usize::from(sign.is_some());
+        if let Some(exponent) = text(at).filter(|text| is_digits(text)) {
+            number.push_str(e);
+            number.push_str(sign.unwrap_or_default());
+            number.push_str(exponent);
+            len = at + 1;
+        }
+    }
+
+    (len > 1).then_some((len, number))
+}
+
+/// Whether `text` is a non-empty run of decimal digits in any script.
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_decimal_digit)
+}
+
+/// Whether `c` is a decimal digit, of the general category Nd. Other numeric
+/// characters, e.g. `²`, `①` or `Ⅳ`, are not part of numbers.
+fn is_decimal_digit(c: char) -> bool {
+    matches!(
+        c,
+        '\u{30}'..='\u{39}' | '\u{660}'..='\u{669}' | '\u{6F0}'..='\u{6F9}' |
+        '\u{7C0}'..='\u{7C9}' | '\u{966}'..='\u{96F}' | '\u{9E6}'..='\u{9EF}' |
+        '\u{A66}'..='\u{A6F}' | '\u{AE6}'..='\u{AEF}' | '\u{B66}'..='\u{B6F}' |
+        '\u{BE6}'..='\u{BEF}' | '\u{C66}'..='\u{C6F}' | '\u{CE6}'..='\u{CEF}' |
+        '\u{D66}'..='\u{D6F}' | '\u{DE6}'..='\u{DEF}' | '\u{E50}'..='\u{E59}' |
+        '\u{ED0}'..='\u{ED9}' | '\u{F20}'..='\u{F29}' | '\u{1040}'..='\u{1049}' |
+        '\u{1090}'..='\u{1099}' | '\u{17E0}'..='\u{17E9}' | '\u{1810}'..='\u{1819}' |
+        '\u{1946}'..='\u{194F}' | '\u{19D0}'..='\u{19D9}' | '\u{1A80}'..='\u{1A89}' |
+        '\u{1A90}'..='\u{1A99}' | '\u{1B50}'..='\u{1B59}' | '\u{1BB0}'..='\u{1BB9}' |
+        '\u{1C40}'..='\u{1C49}' | '\u{1C50}'..='\u{1C59}' | '\u{A620}'..='\u{A629}' |
+        '\u{A8D0}'..='\u{A8D9}' | '\u{A900}'..='\u{A909}' | '\u{A9D0}'..='\u{A9D9}' |
+        '\u{A9F0}'..='\u{A9F9}' | '\u{AA50}'..='\u{AA59}' | '\u{ABF0}'..='\u{ABF9}' |
+        '\u{FF10}'..='\u{FF19}' | '\u{104A0}'..='\u{104A9}' | '\u{10D30}'..='\u{10D39}' |
+        '\u{11066}'..='\u{1106F}' | '\u{110F0}'..='\u{110F9}' | '\u{11136}'..='\u{1113F}' |
+        '\u{111D0}'..='\u{111D9}' | '\u{112F0}'..='\u{112F9}' | '\u{11450}'..='\u{11459}' |
+        '\u{114D0}'..='\u{114D9}' | '\u{11650}'..='\u{11659}' | '\u{116C0}'..='\u{116C9}' |
+        '\u{11730}'..='\u{11739}' | '\u{118E0}'..='\u{118E9}' | '\u{11950}'..='\u{11959}' |
+        '\u{11C50}'..='\u{11C59}' | '\u{11D50}'..='\u{11D59}' | '\u{11DA0}'..='\u{11DA9}' |
+        '\u{11F50}'..='\u{11F59}' | '\u{16A60}'..='\u{16A69}' | '\u{16AC0}'..='\u{16AC9}' |
+        '\u{16B50}'..='\u{16B59}' | '\u{1D7CE}'..='\u{1D7FF}' | '\u{1E140}'..='\u{1E149}' |
+        '\u{1E2F0}'..='\u{1E2F9}' | '\u{1E4F0}'..='\u{1E4F9}' | '\u{1E950}'..='\u{1E959}' |
+        '\u{1FBF0}'..='\u{1FBF9}'
+    )
+}
+
+fn is_fraction(c: char) -> bool {
+    matches!(c, '¼'..='¾' | '⅐'..='⅟' | '↉')
+}
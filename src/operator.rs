@@ -0,0 +1,86 @@
+//! Operator properties following Typst's layout of attachments.
+
+use typst::foundations::{Chainable, Content, StyleChain, StyledElem};
+use typst::math::{ClassElem, LimitsElem, OpElem, StretchElem};
+use typst::text::TextElem;
+use unicode_math_class::MathClass;
+
+/// Where the attachments of a base go, mirroring `Limits` in Typst's layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Limits {
+    /// Above and below the base, e.g. for relations or `limits(x)`.
+    Always,
+    /// Above and below the base in display style, as scripts otherwise, e.g. for
+    /// large operators such as `sum` or `lim`.
+    Display,
+    /// As scripts, e.g. for integrals.
+    Never,
+}
+
+impl Limits {
+    /// The default placement of attachments to `c`.
+    pub(crate) fn for_char(c: char) -> Self {
+        match unicode_math_class::class(c) {
+            Some(MathClass::Large) if is_integral(c) => Self::Never,
+            Some(class) => Self::for_class(class),
+            None => Self::Never,
+        }
+    }
+
+    pub(crate) fn for_class(class: MathClass) -> Self {
+        match class {
+            MathClass::Large => Self::Display,
+            MathClass::Relation => Self::Always,
+            _ => Self::Never,
+        }
+    }
+
+    /// The placement of attachments to `base`.
+    pub(crate) fn for_content(base: &Content, style_chain: StyleChain) -> Self {
+        if let Some(styled) = base.to_packed::<StyledElem>() {
+            // Styles keep the limits of their content, e.g. `upright(lim)`.
+            Self::for_content(styled.child(), styled.styles().chain(&style_chain))
+        } else if let Some(limits) = base.to_packed::<LimitsElem>() {
+            if limits.inline(style_chain) {
+                Self::Always
+            } else {
+                Self::Display
+            }
+        } else if let Some(op) = base.to_packed::<OpElem>() {
+            if op.limits(style_chain) {
+                Self::Display
+            } else {
+                Self::Never
+            }
+        } else if let Some(class) = base.to_packed::<ClassElem>() {
+            Self::for_class(*class.class())
+        } else if base.is::<StretchElem>() {
+            // Stretched symbols are arrows or braces, which are annotated above
+            // and below.
+            Self::Always
+        } else if let Some(text) = base.to_packed::<TextElem>() {
+            let mut chars = text.text().chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Self::for_char(c),
+                _ => Self::Never,
+            }
+        } else {
+            Self::Never
+        }
+    }
+
+    /// Whether the attachments go above and below the base, `display` whether it is
+    /// in display size, e.g. not in a fraction or a script.
+    pub(crate) fn active(self, display: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Display => display,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Integrals keep their attachments as scripts, even in display style.
+pub(crate) fn is_integral(c: char) -> bool {
+    matches!(c, '∫'..='∳' | '⨋'..='⨜')
+}
@@ -0,0 +1,65 @@
+/// The spacing equivalent of a combining accent, which Typst normalizes accents
+/// to, e.g. `U+0303` for `tilde`. MathML renderers expect spacing characters in
+/// `<mo>`. Other characters are kept.
+pub(crate) fn spacing_char(c: char) -> char {
+    match c {
+        // Above.
+        '\u{0300}' => '`',
+        '\u{0301}' => '´',
+        '\u{0302}' => '^',
+        '\u{0303}' => '~',
+        '\u{0304}' => '¯',
+        '\u{0305}' => '‾',
+        '\u{0306}' => '˘',
+        '\u{0307}' => '˙',
+        '\u{0308}' => '¨',
+        '\u{030A}' => '˚',
+        '\u{030B}' => '˝',
+        '\u{030C}' => 'ˇ',
+        '\u{20D0}' => '↼',
+        '\u{20D1}' => '⇀',
+        '\u{20D6}' => '←',
+        '\u{20D7}' => '→',
+        '\u{20E1}' => '↔',
+        // Below.
+        '\u{0323}' => '.',
+        '\u{0324}' => '¨',
+        '\u{032D}' => '^',
+        '\u{032E}' => '˘',
+        '\u{0330}' => '~',
+        '\u{0331}' => 'ˍ',
+        '\u{0332}' => '_',
+        '\u{20EC}' => '⇁',
+        '\u{20ED}' => '↽',
+        '\u{20EE}' => '←',
+        '\u{20EF}' => '→',
+        _ => c,
+    }
+}
+
+/// Whether the combining accent `c` goes below its base, e.g. `U+0330`.
+pub(crate) fn is_below(c: char) -> bool {
+    matches!(c, '\u{0316}'..='\u{0333}' | '\u{20EC}'..='\u{20EF}')
+}
+
+/// Whether the spacing accent `c` can stretch over a wide base, e.g. `~` but not
+/// `˙`.
+pub(crate) fn is_wide(c: char) -> bool {
+    matches!(
+        c,
+        '^' | '~'
+            | '¯'
+            | '‾'
+            | '˘'
+            | 'ˇ'
+            | 'ˍ'
+            | '_'
+            | '←'
+            | '→'
+            | '↔'
+            | '↼'
+            | '⇀'
+            | '↽'
+            | '⇁'
+    )
+}
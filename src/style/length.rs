@@ -0,0 +1,54 @@
+use typst::{
+    foundations::StyleChain,
+    layout::{Abs, Rel},
+    text::TextElem,
+};
+
+/// `abs` in em of the current font size.
+pub(crate) fn to_em(abs: Abs, style_chain: StyleChain) -> f64 {
+    let font_size = TextElem::size_in(style_chain);
+    if font_size.is_zero() {
+        0.0
+    } else {
+        abs / font_size
+    }
+}
+
+/// A resolved Typst length as a CSS length, e.g. `0.5em`.
+pub(crate) fn em(abs: Abs, style_chain: StyleChain) -> String {
+    format!("{}em", number(to_em(abs, style_chain)))
+}
+
+/// A resolved relative Typst length as a CSS length-percentage, e.g. `120%`,
+/// `0.5em` or `calc(120% + 0.5em)`.
+pub(crate) fn rel(rel: Rel<Abs>, style_chain: StyleChain) -> String {
+    let percent = format!("{}%", number(rel.rel.get() * 100.0));
+    if rel.abs.is_zero() {
+        percent
+    } else if rel.rel.is_zero() {
+        em(rel.abs, style_chain)
+    } else {
+        format!("calc({} + {})", percent, em(rel.abs, style_chain))
+    }
+}
+
+/// `value` rounded to three decimals, without trailing zeros.
+pub(crate) fn number(value: f64) -> String {
+    let rounded = (value * 1000.0).round() / 1000.0;
+    // Avoid `-0`.
+    if rounded == 0.0 {
+        "0".to_string()
+    } else {
+        rounded.to_string()
+    }
+}
+
+/// Whether `rel` is exactly `em` of the current font size, e.g. a default gap.
+pub(crate) fn is_em(rel: Rel<Abs>, em: f64, style_chain: StyleChain) -> bool {
+    rel.rel.is_zero() && (to_em(rel.abs, style_chain) - em).abs() < 1e-6
+}
+
+/// Half of `rel`, e.g. the padding on each side of a gap.
+pub(crate) fn half(rel: Rel<Abs>) -> Rel<Abs> {
+    Rel::new(rel.rel * 0.5, rel.abs * 0.5)
+}
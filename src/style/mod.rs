@@ -1,2 +1,4 @@
+pub (crate) mod accent;
 pub (crate) mod character_variant;
-pub (crate) mod italic_exception;
\ No newline at end of file
+pub (crate) mod italic_exception;
+pub (crate) mod length;
@@ -34,3 +34,32 @@ test_math_function!(
     math_accent, test_math_accent;
     accent_base, test_accent_base;
 );
+
+#[test]
+fn test_spacing_accents() {
+    let mathml = convert_to_mathml(r#"$ tilde(a) + accent(a, \u{0303}) + dot(x) $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches(r#"<mi>a</mi><mo>~</mo></mover>"#).count(), 2);
+    assert!(mathml.contains(r#"<mi>x</mi><mo>˙</mo></mover>"#));
+    assert!(!mathml.contains('\u{0303}'));
+}
+
+#[test]
+fn test_wide_accents() {
+    let mathml = convert_to_mathml("$ arrow(A B C) + hat(x y) + dot(x y) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mo stretchy="true">→</mo></mover>"#));
+    assert!(mathml.contains(r#"<mo stretchy="true">^</mo></mover>"#));
+    assert!(mathml.contains(r#"<mo>˙</mo></mover>"#));
+
+    let mathml = convert_to_mathml("$ accent(x, hat, size: #200%) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mo stretchy="true" minsize="200%" maxsize="200%">^</mo>"#));
+}
+
+#[test]
+fn test_bottom_accents() {
+    let mathml = convert_to_mathml(r#"$ accent(x, \u{0330}) $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<munder accentunder="true"><mi>x</mi><mo>~</mo></munder>"#));
+}
@@ -0,0 +1,72 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    mathml::convert_to_mathml,
+};
+
+#[test]
+fn test_align_points() {
+    let mathml = convert_to_mathml(r#"$ a &= b \ &= c + d $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mtable columnspacing="0">"#));
+    assert!(mathml.contains(
+        r#"<mtd columnalign="right" style="text-align: right; padding-right: 0"><mi>a</mi></mtd>"#
+    ));
+    assert!(
+        mathml.contains(r#"<mtd columnalign="left" style="text-align: left; padding-left: 0"><mo"#)
+    );
+    assert_eq!(mathml.matches("<mtr>").count(), 2);
+
+    // A trailing linebreak does not add a row.
+    let mathml = convert_to_mathml(r#"$ a &= b \ &= c \ $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches("<mtr>").count(), 2);
+
+    // Without linebreaks, alignment points have no effect.
+    let mathml = convert_to_mathml("$ a &= b $", false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("mtable"));
+}
+
+#[test]
+fn test_align_nested() {
+    let mathml = convert_to_mathml(r#"$ bold(a &= b) \ c &= d $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches("<mtr>").count(), 2);
+    assert_eq!(mathml.matches("<mtd").count(), 4);
+
+    // Rows with fewer alignment points are padded with empty cells.
+    let mathml = convert_to_mathml(r#"$ a &= b & c \ d &= e $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches("<mtd").count(), 6);
+}
+
+#[test]
+fn test_align_cases_and_mat() {
+    let mathml = convert_to_mathml("$ f(x) = cases(1 &\"if\" x > 0, 0 &\"else\") $", false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches("<mtd").count(), 4);
+    assert!(mathml.contains(r#"<mtable columnspacing="0">"#));
+
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter
+        .convert("$ mat(a &= b, 1; c &= d, 2) $")
+        .unwrap()
+        .mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mtable columnspacing="0 0.5em">"#));
+    assert_eq!(mathml.matches("<mtd").count(), 6);
+}
+
+#[test]
+fn test_align_in_delimiters() {
+    let mathml = convert_to_mathml(r#"$ f(x) = {a &= b \ c &= d} $"#, false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("merror"));
+    assert!(mathml.contains(
+        r#"<mo fence="true" form="prefix" stretchy="true" symmetric="true">{</mo><mtable"#
+    ));
+    assert_eq!(mathml.matches("<mtd").count(), 4);
+}
@@ -37,3 +37,51 @@ test_math_function!(
     attach_script, test_attach_script;
     attach_limits, test_attach_limits;
 );
+
+#[test]
+fn test_operator_limits() {
+    let mathml = convert_to_mathml("$ sum_(i=1)^n i + integral_0^1 x + lim_(x -> 0) x $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(
+        r#"<munderover><mo largeop="true" symmetric="true" movablelimits="true">∑</mo>"#
+    ));
+    assert!(mathml.contains(r#"<msubsup><mo largeop="true" symmetric="true">∫</mo>"#));
+    assert!(mathml.contains("<munder><mi>lim</mi>"));
+
+    // Inline equations put the limits of large operators into scripts.
+    let mathml = convert_to_mathml("$sum_(i=1)^n i + product_i i$", false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("munder"));
+
+    // So do fractions and scripts of block equations, e.g. for `lim`, which is an
+    // identifier without `movablelimits`.
+    let mathml = convert_to_mathml("$ frac(lim_(x -> 0) x, 2) + e^(sum_i i) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<msub><mi>lim</mi>"));
+    assert!(!mathml.contains("munder"));
+
+    // Relations and `limits` keep their limits everywhere.
+    let mathml = convert_to_mathml("$x =^\"def\" y + limits(A)_1$", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mover><mo movablelimits="false">=</mo>"#));
+    assert!(mathml.contains("<munder><mi>A</mi>"));
+
+    // Classes and styles keep the limits of their content.
+    let mathml = convert_to_mathml(
+        r#"$ class("large", x)_a^b + upright(lim)_(x -> 0) $"#,
+        false,
+    );
+    println!("{}", mathml);
+    assert!(mathml.contains("<munderover>"));
+    assert!(mathml.contains("<munder>"));
+    assert!(!mathml.contains("<msub"));
+
+    // The size set by `display` and `inline` counts.
+    let mathml = convert_to_mathml("$display(lim)_(x -> 0) + display(sum)_i$", false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches("<munder>").count(), 2);
+    assert!(mathml.contains(r#"movablelimits="false""#));
+    let mathml = convert_to_mathml("$ inline(sum_i i) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<msub>"));
+}
@@ -0,0 +1,35 @@
+use convert_typst_math_to_mathml::{converter::Converter, eval_math::evict_cache};
+
+#[test]
+fn test_memoized_conversion() {
+    let converter = Converter::default();
+    let inputs: Vec<_> = (0..200)
+        .map(|i| format!("$ x_{} = {}^2 $", i % 20, i % 20))
+        .collect();
+    let convert_all = || -> Vec<_> {
+        inputs
+            .iter()
+            .map(|input| converter.convert(input).unwrap().mathml)
+            .collect()
+    };
+
+    // Repeated inputs reuse the memoized evaluation, which gives the same output as
+    // evaluating them again after the cache is cleared.
+    let memoized = convert_all();
+    evict_cache(0);
+    let evaluated = convert_all();
+    assert_eq!(memoized, evaluated);
+}
+
+#[test]
+fn test_evict_cache() {
+    let converter = Converter::default();
+    let before = converter.convert("$ a + b $").unwrap().mathml;
+    // Aging the cache keeps recently used entries, clearing it drops all of them.
+    evict_cache(10);
+    let aged = converter.convert("$ a + b $").unwrap().mathml;
+    evict_cache(0);
+    let after = converter.convert("$ a + b $").unwrap().mathml;
+    assert_eq!(before, aged);
+    assert_eq!(before, after);
+}
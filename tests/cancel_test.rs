@@ -1,4 +1,7 @@
-use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    mathml::convert_to_mathml,
+};
 
 macro_rules! test_math_function {
     ($( $func_name:ident, $test_func_name:ident );* $(;)?) => {
@@ -72,3 +75,62 @@ test_math_function!(
     cancel_angle, test_cancel_angle;
     cancel_stroke, test_cancel_stroke;
 );
+
+#[test]
+fn test_cancel_parameters() {
+    let mathml = convert_to_mathml("$ cancel(Pi, cross: #true) $", false);
+    assert!(mathml.contains(r#"notation="updiagonalstrike downdiagonalstrike""#));
+    assert_eq!(mathml.matches("cancel-wrapper-placeholder").count(), 2);
+
+    let mathml = convert_to_mathml("$ cancel(x, inverted: #true) $", false);
+    assert!(mathml.contains(r#"notation="downdiagonalstrike""#));
+    assert!(mathml.contains("transform: scaleX(-1)"));
+
+    let mathml = convert_to_mathml("$ cancel(1/(1+x), angle: #(a => a + 45deg)) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"notation="horizontalstrike""#));
+    assert!(mathml.contains("rotate(90deg)"));
+
+    let mathml = convert_to_mathml(
+        r#"$ cancel(x, stroke: #(paint: red, thickness: 1.5pt, dash: "dashed")) $"#,
+        false,
+    );
+    println!("{}", mathml);
+    assert!(mathml.contains("--equation-typst-cancel-color: #ff4136"));
+    assert!(mathml.contains("--equation-typst-cancel-thickness: 0.136em"));
+    assert!(mathml.contains("--equation-typst-cancel-style: dashed"));
+    // The default diagonal is not a border, its dashes come from a class.
+    assert!(mathml.contains(r#"class="equation-typst-cancel equation-typst-cancel-dashed""#));
+
+    let mathml = convert_to_mathml(r#"$ cancel(x, stroke: #(dash: "dotted")) $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains("--equation-typst-cancel-style: dotted"));
+    assert!(mathml.contains("equation-typst-cancel-dotted"));
+}
+
+#[test]
+fn test_cancel_stroke_full() {
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let converted = converter
+        .convert(r#"$ cancel(x, stroke: #(paint: red, thickness: 1.5pt, dash: "dashed")) $"#)
+        .unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted
+        .mathml
+        .contains(r#"<menclose notation="updiagonalstrike" mathcolor="#ff4136"><mrow mathcolor="#000000"><mi>x</mi></mrow></menclose>"#));
+    assert_eq!(converted.unsupported.get("cancel.stroke"), Some(&1));
+
+    // The default stroke is not reported.
+    let converted = converter.convert("$ cancel(x) $").unwrap();
+    assert!(converted.unsupported.is_empty());
+}
+
+#[test]
+fn test_cancel_polyfill() {
+    let css = convert_typst_math_to_mathml::css::polyfill("my-math");
+    assert!(css.contains(".my-math-cancel-line"));
+    assert!(!css.contains("equation-typst"));
+}
@@ -0,0 +1,92 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_typst-mathml"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_stdin() {
+    let output = run(&["--block"], "$x$ and $y$");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    println!("{}", stdout);
+    assert!(output.status.success());
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout
+        .lines()
+        .all(|line| line.starts_with(r#"<math display="block">"#)));
+}
+
+#[test]
+fn test_error_exit_code() {
+    let output = run(&[], "$ a + undefined $");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    println!("{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("undefined"));
+}
+
+#[test]
+fn test_json() {
+    let output = run(&["--json", "-"], "$ a + undefined $");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    println!("{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let diagnostic = &json["results"][0]["diagnostics"][0];
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["location"]["start"]["column"], 6);
+    assert_eq!(diagnostic["location"]["range"]["start"], 6);
+    assert_eq!(diagnostic["location"]["range"]["end"], 15);
+    assert_eq!(diagnostic["trace"], serde_json::json!([]));
+}
+
+#[test]
+fn test_annotation_on_one_line() {
+    let output = run(&["--annotation"], "$ a + b\n  = c $\n\n$x$");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    println!("{}", stdout);
+    assert!(output.status.success());
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.lines().all(|line| line.starts_with("<math")));
+    assert!(stdout.contains("a + b&#xA;  = c"));
+}
+
+#[test]
+fn test_invalid_argument() {
+    let output = run(&["--unknown"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_json_preamble_error() {
+    let path = std::env::temp_dir().join("typst-mathml-invalid-preamble.typ");
+    std::fs::write(&path, "#let f(x) = x\n#let g = (").unwrap();
+    let output = run(
+        &["--json", "--preamble", path.to_str().unwrap()],
+        "$ f(x) $",
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    println!("{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let diagnostic = &json["preamble"][0];
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["location"]["origin"], "preamble");
+    assert_eq!(json["results"], serde_json::json!([]));
+}
@@ -0,0 +1,86 @@
+use convert_typst_math_to_mathml::converter::{
+    ConvertOptions, Converter, DisplayMode, TargetProfile, UnknownElementPolicy,
+};
+
+#[test]
+fn test_display_override() {
+    let converter = Converter::new(ConvertOptions {
+        display: DisplayMode::Inline,
+        ..Default::default()
+    });
+    let mathml = converter.convert("$ x = 1 $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.starts_with("<math><semantics>"));
+
+    let converter = Converter::new(ConvertOptions {
+        display: DisplayMode::Block,
+        ..Default::default()
+    });
+    let mathml = converter.convert("$x = 1$").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.starts_with(r#"<math display="block">"#));
+}
+
+#[test]
+fn test_xmlns_and_annotation() {
+    let converter = Converter::new(ConvertOptions {
+        xmlns: true,
+        annotation: true,
+        ..Default::default()
+    });
+    let mathml = converter.convert("$x < y$").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.starts_with(r#"<math xmlns="http://www.w3.org/1998/Math/MathML">"#));
+    assert!(
+        mathml.contains(r#"<annotation encoding="application/x-typst">$x &lt; y$</annotation>"#)
+    );
+}
+
+#[test]
+fn test_class_prefix() {
+    let converter = Converter::new(ConvertOptions {
+        class_prefix: "my-math".to_string(),
+        ..Default::default()
+    });
+    let mathml = converter.convert("$ cancel(x) $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"class="my-math-cancel""#));
+}
+
+#[test]
+fn test_full_profile() {
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter.convert("$ mat(1, 2; 3, 4) $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mtd columnalign="center">"#));
+    assert!(!mathml.contains("style="));
+}
+
+#[test]
+fn test_reuse_converter() {
+    let converter = Converter::new(ConvertOptions {
+        unknown_element: UnknownElementPolicy::Skip,
+        ..Default::default()
+    });
+    for input in ["$ a $", "$ b $", "$ c $"] {
+        println!("{}", converter.convert(input).unwrap().mathml);
+    }
+}
+
+#[test]
+fn test_convert_into() {
+    let converter = Converter::default();
+    let input = "$ mat(1, 2; 3, 4) $";
+    let expected = converter.convert(input).unwrap().mathml;
+
+    let mut out = String::from("<p>");
+    converter.convert_into(input, &mut out).unwrap();
+    assert_eq!(out, format!("<p>{}", expected));
+
+    let mut bytes = Vec::new();
+    converter.convert_into_io(input, &mut bytes).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+}
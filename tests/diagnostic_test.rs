@@ -0,0 +1,35 @@
+use convert_typst_math_to_mathml::{
+    diagnostic::{ConvertError, Severity},
+    mathml::try_convert_to_mathml,
+};
+
+#[test]
+fn test_unknown_variable() {
+    let input = "$ a +\n  foo $";
+    let err = try_convert_to_mathml(input, false).unwrap_err();
+    println!("{}", err);
+
+    let ConvertError::Compile(errors) = err else {
+        panic!("expected a compile error");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].severity, Severity::Error);
+    assert!(errors[0].message.contains("foo"));
+
+    let location = errors[0].location.as_ref().unwrap();
+    assert_eq!(&input[location.range.clone()], "foo");
+    assert_eq!((location.start.line, location.start.column), (1, 2));
+}
+
+#[test]
+fn test_no_equation() {
+    let err = try_convert_to_mathml("just text", false).unwrap_err();
+    assert!(matches!(err, ConvertError::NoEquation));
+}
+
+#[test]
+fn test_success_without_warnings() {
+    let converted = try_convert_to_mathml("$ x^2 $", false).unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted.warnings.is_empty());
+}
@@ -0,0 +1,41 @@
+use convert_typst_math_to_mathml::converter::Converter;
+
+const CHAPTER: &str = r#"= Pythagoras
+
+In a right triangle with legs $a$ and $b$, the hypotenuse $c$ satisfies
+$ a^2 + b^2 = c^2 $ <pythagoras>
+
+#set math.equation(numbering: "(1)")
+Hence
+$ c = sqrt(a^2 + b^2) $
+"#;
+
+#[test]
+fn test_convert_all() {
+    let document = Converter::default().convert_all(CHAPTER).unwrap();
+    for equation in &document.equations {
+        println!(
+            "{:?} {:?} {}",
+            equation.label, equation.location, equation.mathml
+        );
+    }
+
+    let equations = &document.equations;
+    assert_eq!(equations.len(), 5);
+    assert_eq!(
+        equations.iter().map(|it| it.block).collect::<Vec<_>>(),
+        [false, false, false, true, true]
+    );
+
+    let location = equations[3].location.as_ref().unwrap();
+    assert_eq!(&CHAPTER[location.range.clone()], "$ a^2 + b^2 = c^2 $");
+    assert_eq!((location.start.line, location.start.column), (3, 0));
+    assert_eq!(equations[3].label.as_deref(), Some("pythagoras"));
+    assert_eq!(equations[4].label, None);
+}
+
+#[test]
+fn test_convert_all_without_equations() {
+    let document = Converter::default().convert_all("no math here").unwrap();
+    assert!(document.equations.is_empty());
+}
@@ -0,0 +1,31 @@
+use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+
+#[test]
+fn test_unary_and_binary() {
+    let mathml = convert_to_mathml("$ -x = a - b $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mrow><mo form="prefix">−</mo><mi>x</mi>"#));
+    assert!(mathml.contains(r#"<mi>a</mi><mo form="infix">−</mo><mi>b</mi>"#));
+
+    // After a relation or an opening delimiter, operators are unary.
+    let mathml = convert_to_mathml("$ a = -b + (-c) $", false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches(r#"<mo form="prefix">−</mo>"#).count(), 2);
+    assert!(mathml.contains(r#"<mo form="infix">+</mo>"#));
+}
+
+#[test]
+fn test_after_scripts() {
+    let mathml = convert_to_mathml("$ a^2 * b +- c $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"</msup><mo form="infix">∗</mo>"#));
+    assert!(mathml.contains(r#"<mo form="infix">±</mo>"#));
+}
+
+#[test]
+fn test_in_scripts() {
+    let mathml = convert_to_mathml("$ x^(-1) + x^+ $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mo form="prefix">−</mo><mn>1</mn>"#));
+    assert!(mathml.contains(r#"<msup><mi>x</mi><mo form="prefix">+</mo></msup>"#));
+}
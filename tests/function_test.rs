@@ -0,0 +1,34 @@
+use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+
+#[test]
+fn test_function_application() {
+    let mathml = convert_to_mathml("$ sin x + cos(x) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mi>sin</mi><mo rspace="0.167em">&#x2061;</mo><mi>x</mi>"#));
+    assert!(mathml.contains("<mi>cos</mi><mo>&#x2061;</mo><mrow>"));
+
+    let mathml = convert_to_mathml("$ lim_(x -> 0) f(x) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"</munder><mo rspace="0.167em">&#x2061;</mo>"#));
+
+    let mathml = convert_to_mathml("$ sin^2 x $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"</msup><mo rspace="0.167em">&#x2061;</mo><mi>x</mi>"#));
+}
+
+#[test]
+fn test_invisible_times() {
+    let mathml = convert_to_mathml("$ 2 x y $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>2</mn><mo>&#x2062;</mo><mi>x</mi><mo>&#x2062;</mo><mi>y</mi>"));
+
+    let mathml = convert_to_mathml("$ 2(a + b) + a^2 b $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>2</mn><mo>&#x2062;</mo><mrow>"));
+    assert!(mathml.contains("</msup><mo>&#x2062;</mo><mi>b</mi>"));
+
+    // `f(x)` may be a function application, so nothing is inserted.
+    let mathml = convert_to_mathml("$ f(x) + a + b $", false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("&#x2062;"));
+}
@@ -0,0 +1,93 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter},
+    handler::{ChildConverter, ElementHandler},
+    node::{Element, Node},
+};
+use typst::{
+    foundations::{Content, StyleChain},
+    math::{CancelElem, FracElem},
+};
+
+#[test]
+fn test_override_cancel() {
+    let mut options = ConvertOptions::default();
+    options
+        .handlers
+        .register_fn("cancel", |content, style_chain, children| {
+            let cancel = content.to_packed::<CancelElem>()?;
+            let body = children.convert_required(cancel.body(), style_chain);
+            Some(
+                Element::new("mrow")
+                    .with_attr("class", "strike")
+                    .with_child(body)
+                    .into(),
+            )
+        });
+    let mathml = Converter::new(options)
+        .convert("$ cancel(x + 1/2) $")
+        .unwrap()
+        .mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mrow class="strike">"#));
+    assert!(mathml.contains("<mfrac>"));
+    assert!(!mathml.contains("menclose"));
+}
+
+/// Writes fractions as `num / denom`, except for numeric ones.
+struct SlashedFrac;
+
+impl ElementHandler for SlashedFrac {
+    fn convert(
+        &self,
+        content: &Content,
+        style_chain: StyleChain,
+        children: &ChildConverter,
+    ) -> Option<Node> {
+        let frac = content.to_packed::<FracElem>()?;
+        let num = children.convert_required(frac.num(), style_chain);
+        if num.is("mn") {
+            return None;
+        }
+
+        Some(
+            Element::new("mrow")
+                .with_child(num)
+                .with_child(Element::new("mo").with_text("/"))
+                .with_child(children.convert_required(frac.denom(), style_chain))
+                .into(),
+        )
+    }
+}
+
+#[test]
+fn test_fall_back_to_builtin() {
+    let mut options = ConvertOptions::default();
+    options.handlers.register("frac", SlashedFrac);
+    let converter = Converter::new(options);
+
+    let mathml = converter.convert("$ a/b $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains("<mo>/</mo>"));
+
+    let mathml = converter.convert("$ 1/2 $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains("<mfrac>"));
+}
+
+#[test]
+fn test_fall_back_counts_once() {
+    // A handler that converts the children before it falls back.
+    let mut options = ConvertOptions::default();
+    options
+        .handlers
+        .register_fn("frac", |content, style_chain, children| {
+            let frac = content.to_packed::<FracElem>()?;
+            children.convert(frac.num(), style_chain);
+            children.convert(frac.denom(), style_chain);
+            None
+        });
+    let converted = Converter::new(options).convert("$ #rect() / 2 $").unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted.mathml.contains("<mfrac>"));
+    assert_eq!(converted.unsupported.get("rect"), Some(&1));
+}
@@ -0,0 +1,54 @@
+use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+
+macro_rules! test_math_function {
+    ($( $func_name:ident, $test_func_name:ident );* $(;)?) => {
+        $(
+            #[test]
+            fn $test_func_name() {
+                let result = $func_name();
+                for item in result {
+                    println!("{}", item);
+                }
+            }
+        )*
+    };
+}
+
+fn math_lr() -> Vec<String> {
+    let inputs = [
+        r#"$ (a/b) $"#,
+        r#"$ abs(x) + norm(x) + floor(x) + ceil(x) $"#,
+        r#"$ lr(size: #2em, [x/y]) $"#,
+        r#"$ {x mid(|) x > 0} $"#,
+    ];
+
+    inputs.map(|input| convert_to_mathml(input, false)).to_vec()
+}
+
+test_math_function!(
+    math_lr, test_math_lr;
+);
+
+#[test]
+fn test_fences() {
+    let mathml = convert_to_mathml("$ abs(x) $", false);
+    assert!(mathml
+        .contains(r#"<mo fence="true" form="prefix" stretchy="true" symmetric="true">|</mo>"#));
+    assert!(mathml
+        .contains(r#"<mo fence="true" form="postfix" stretchy="true" symmetric="true">|</mo>"#));
+    assert!(!mathml.contains("minsize"));
+}
+
+#[test]
+fn test_size() {
+    let mathml = convert_to_mathml("$ lr(size: #200%, (x/y)) $", false);
+    println!("{}", mathml);
+    assert_eq!(
+        mathml.matches(r#"minsize="200%" maxsize="200%""#).count(),
+        2
+    );
+
+    let mathml = convert_to_mathml("$ lr(size: #150%, {x mid(|) x > 0}) $", false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches(r#"minsize="150%""#).count(), 3);
+}
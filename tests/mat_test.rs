@@ -0,0 +1,142 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    diagnostic::ConvertError,
+    mathml::convert_to_mathml,
+};
+
+macro_rules! test_math_function {
+    ($( $func_name:ident, $test_func_name:ident );* $(;)?) => {
+        $(
+            #[test]
+            fn $test_func_name() {
+                let result = $func_name();
+                for item in result {
+                    println!("{}", item);
+                }
+            }
+        )*
+    };
+}
+
+fn mat_augment() -> Vec<String> {
+    let inputs = [
+        r#"$ mat(1, 0, 1; 0, 1, 2; augment: #2) $"#,
+        r#"$ mat(0, 0, 0; 1, 1, 1; augment: #(hline: 1, stroke: 2pt + green)) $"#,
+        r#"$ mat(1, 0, 0, 0; 0, 1, 0, 0; 0, 0, 1, 1; augment: #(hline: -1, vline: (1, -1))) $"#,
+    ];
+
+    inputs.map(|input| convert_to_mathml(input, false)).to_vec()
+}
+
+fn mat_gap() -> Vec<String> {
+    let inputs = [
+        r#"$ mat(1, 2; 3, 4; row-gap: #1em) $"#,
+        r#"$ mat(1, 2; 3, 4; column-gap: #1em) $"#,
+        r#"$ mat(1, 2; 3, 4; gap: #1em) $"#,
+        r#"$ vec(1, 2, 3, gap: #1em) $"#,
+    ];
+
+    inputs.map(|input| convert_to_mathml(input, false)).to_vec()
+}
+
+test_math_function!(
+    mat_augment, test_mat_augment;
+    mat_gap, test_mat_gap;
+);
+
+#[test]
+fn test_augment_borders() {
+    let mathml = convert_to_mathml("$ mat(1, 0, 1; 0, 1, 2; augment: #2) $", false);
+    assert_eq!(
+        mathml
+            .matches("border-right: 0.05em solid currentColor")
+            .count(),
+        2
+    );
+
+    let mathml = convert_to_mathml(
+        "$ mat(1, 2; 3, 4; augment: #(hline: -1, vline: -1, stroke: 2pt + green)) $",
+        false,
+    );
+    println!("{}", mathml);
+    assert_eq!(
+        mathml
+            .matches("border-bottom: 0.182em solid #2ecc40")
+            .count(),
+        2
+    );
+    assert_eq!(
+        mathml
+            .matches("border-right: 0.182em solid #2ecc40")
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_full_profile() {
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter
+        .convert("$ mat(1, 0, 0; 0, 1, 0; 0, 0, 1; augment: #(hline: 1, vline: 2), gap: #1em) $")
+        .unwrap()
+        .mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"rowlines="solid none""#));
+    assert!(mathml.contains(r#"columnlines="none solid""#));
+    assert!(mathml.contains(r#"rowspacing="1em""#));
+    assert!(mathml.contains(r#"columnspacing="1em""#));
+
+    // Dotted dash patterns are dotted lines.
+    let mathml = converter
+        .convert(r#"$ mat(1, 2; 3, 4; augment: #(vline: 1, stroke: (dash: "dotted"))) $"#)
+        .unwrap()
+        .mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"columnlines="dotted""#));
+
+    let mathml = convert_to_mathml(
+        r#"$ mat(1, 2; 3, 4; augment: #(vline: 1, stroke: (dash: "dotted"))) $"#,
+        false,
+    );
+    println!("{}", mathml);
+    assert!(mathml.contains("border-right: 0.05em dotted currentColor"));
+}
+
+#[test]
+fn test_default_gap() {
+    let mathml = convert_to_mathml("$ mat(1, 2; 3, 4) $", false);
+    assert!(!mathml.contains("padding"));
+
+    let mathml = convert_to_mathml("$ vec(1, 2, gap: #1em) $", false);
+    assert!(mathml.contains("padding-block: 0.5em"));
+}
+
+#[test]
+fn test_augment_out_of_range() {
+    let converter = Converter::default();
+    for (input, message) in [
+        (
+            "$ mat(1, 2; 3, 4; augment: #2) $",
+            "cannot draw a vertical line after column 2 of a matrix with 2 columns",
+        ),
+        (
+            "$ mat(1, 2; 3, 4; augment: #(hline: 0)) $",
+            "cannot draw a horizontal line after row 0 of a matrix with 2 rows",
+        ),
+        (
+            "$ mat(1, 2; 3, 4; augment: #(hline: -2)) $",
+            "cannot draw a horizontal line after row 0 of a matrix with 2 rows",
+        ),
+    ] {
+        let err = converter.convert(input).unwrap_err();
+        println!("{}", err);
+        let ConvertError::Compile(errors) = err else {
+            panic!("expected an error for the augment offset");
+        };
+        assert_eq!(errors[0].message, message);
+        assert!(errors[0].location.is_some());
+    }
+}
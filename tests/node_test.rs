@@ -0,0 +1,62 @@
+use convert_typst_math_to_mathml::{
+    converter::Converter,
+    node::{walk_element, walk_element_mut, Element, Node, Visitor, VisitorMut},
+};
+
+#[test]
+fn test_serialize_escapes() {
+    let math = Element::new("math").with_child(
+        Element::new("mtext")
+            .with_attr("title", r#"a "b""#)
+            .with_text("x < y\u{A0}& z"),
+    );
+    assert_eq!(
+        math.to_string(),
+        r#"<math><mtext title="a &quot;b&quot;">x &lt; y&#xA0;&amp; z</mtext></math>"#
+    );
+}
+
+#[test]
+fn test_visitor() {
+    struct CountIdentifiers(usize);
+
+    impl Visitor for CountIdentifiers {
+        fn visit_element(&mut self, element: &Element) {
+            if element.name == "mi" {
+                self.0 += 1;
+            }
+            walk_element(self, element);
+        }
+    }
+
+    let tree = Converter::default().convert_to_tree("$ x + y = z $").unwrap();
+    println!("{}", tree.math);
+    let mut counter = CountIdentifiers(0);
+    Node::from(tree.math).accept(&mut counter);
+    assert_eq!(counter.0, 3);
+}
+
+#[test]
+fn test_visitor_mut() {
+    struct AddClass;
+
+    impl VisitorMut for AddClass {
+        fn visit_element_mut(&mut self, element: &mut Element) {
+            if element.name == "mfrac" {
+                element.set_attr("class", "fraction");
+            }
+            walk_element_mut(self, element);
+        }
+    }
+
+    let converter = Converter::default();
+    let mut tree = converter.convert_to_tree("$ 1/2 $").unwrap();
+    AddClass.visit_element_mut(&mut tree.math);
+    let mathml = tree.math.to_string();
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mfrac class="fraction">"#));
+    assert_eq!(
+        converter.convert("$ 1/2 $").unwrap().mathml,
+        mathml.replace(r#" class="fraction""#, "")
+    );
+}
@@ -0,0 +1,61 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, DecimalSeparator},
+    mathml::convert_to_mathml,
+};
+
+#[test]
+fn test_numbers() {
+    let mathml = convert_to_mathml("$ 1,000.5 + 3.14e-5 + 3.141 592 + 1½ + ½ $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>1,000.5</mn>"));
+    assert!(mathml.contains("<mn>3.14e−5</mn>"));
+    assert!(mathml.contains("<mn>3.141\u{202F}592</mn>"));
+    assert!(mathml.contains("<mn>1½</mn>"));
+    assert!(mathml.contains("<mn>½</mn>"));
+
+    // Lists of numbers are not merged.
+    let mathml = convert_to_mathml("$ (1, 2) + f(1,2) $", false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("<mn>1,2</mn>"));
+
+    // Nor are numbers separated by spaces only.
+    let mathml = convert_to_mathml("$ 2 345 + x $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>2</mn>"));
+    assert!(mathml.contains("<mn>345</mn>"));
+    assert!(!mathml.contains("\u{202F}"));
+}
+
+#[test]
+fn test_numbers_in_delimiters() {
+    let mathml = convert_to_mathml("$ f(1,000) + (3.14e-5) + [3.141 592] + (1, 2) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>1,000</mn>"));
+    assert!(mathml.contains("<mn>3.14e−5</mn>"));
+    assert!(mathml.contains("<mn>3.141\u{202F}592</mn>"));
+    assert!(!mathml.contains("<mn>1,2</mn>"));
+}
+
+#[test]
+fn test_not_numbers() {
+    let mathml = convert_to_mathml(r#"$ "inf" + "NaN" + e $"#, false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("<mn>"));
+
+    // Numeric characters that are not decimal digits.
+    let mathml = convert_to_mathml("$ ① + Ⅳ + x ² $", false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("<mn>"));
+}
+
+#[test]
+fn test_decimal_comma() {
+    let converter = Converter::new(ConvertOptions {
+        decimal_separator: DecimalSeparator::Comma,
+        ..Default::default()
+    });
+    let mathml = converter.convert("$ 1.000,5 + 3,14 $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains("<mn>1.000,5</mn>"));
+    assert!(mathml.contains("<mn>3,14</mn>"));
+}
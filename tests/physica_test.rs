@@ -1,4 +1,9 @@
-use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+use convert_typst_math_to_mathml::{
+    converter::Converter,
+    diagnostic::{ConvertError, Origin},
+    eval_math::evict_cache,
+    mathml::convert_to_mathml,
+};
 
 macro_rules! test_math_function {
     ($( $func_name:ident, $test_func_name:ident );* $(;)?) => {
@@ -16,6 +21,12 @@ macro_rules! test_math_function {
 
 const BASIC: &str = include_str!("./physica.typ");
 
+fn physica_converter() -> Converter {
+    let mut converter = Converter::default();
+    converter.set_preamble(BASIC).unwrap();
+    converter
+}
+
 fn physica_transpose() -> Vec<String> {
     let base = BASIC.to_string();
 
@@ -32,7 +43,65 @@ fn physica_fxydxdy() -> Vec<String> {
     inputs.map(|input| convert_to_mathml(input, false)).to_vec()
 }
 
+fn physica_preamble() -> Vec<String> {
+    let converter = physica_converter();
+
+    let inputs = [r#"$ A^T $"#, r#"$ f(x,y) dd(x,y), $"#, r#"$ dv(f, x) $"#];
+
+    inputs
+        .map(|input| converter.convert(input).unwrap().mathml)
+        .to_vec()
+}
+
 test_math_function!(
     physica_transpose, test_physica_transpose;
     physica_fxydxdy, test_physica_fxydxdy;
+    physica_preamble, test_physica_preamble;
 );
+
+#[test]
+fn test_preamble_matches_prepended_source() {
+    let converter = physica_converter();
+    let input = r#"$ f(x,y) dd(x,y), $"#;
+
+    assert_eq!(
+        converter.convert(input).unwrap().mathml,
+        convert_to_mathml(&(BASIC.to_string() + input), false)
+    );
+}
+
+#[test]
+fn test_preamble_error() {
+    let mut converter = Converter::default();
+    let err = converter
+        .set_preamble("#let f(x) = x\n#let g = (")
+        .unwrap_err();
+    println!("{}", err);
+    assert!(matches!(err, ConvertError::Preamble(_)));
+}
+
+#[test]
+fn test_equation_error_with_preamble() {
+    let converter = physica_converter();
+    let input = "$ dd(x) +\n  undefined $";
+    let ConvertError::Compile(errors) = converter.convert(input).unwrap_err() else {
+        panic!("expected an error in the equation");
+    };
+    println!("{}", errors[0]);
+
+    let location = errors[0].location.as_ref().unwrap();
+    assert_eq!(location.origin, Origin::Input);
+    assert_eq!(&input[location.range.clone()], "undefined");
+    assert_eq!((location.start.line, location.start.column), (1, 2));
+}
+
+#[test]
+fn test_preamble_after_evict_cache() {
+    let converter = physica_converter();
+    let input = r#"$ dv(f, x) $"#;
+    let before = converter.convert(input).unwrap().mathml;
+
+    // The bindings of the preamble are kept by the converter, not by the cache.
+    evict_cache(0);
+    assert_eq!(converter.convert(input).unwrap().mathml, before);
+}
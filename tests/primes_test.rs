@@ -0,0 +1,33 @@
+use convert_typst_math_to_mathml::mathml::convert_to_mathml;
+
+#[test]
+fn test_primes_as_superscript() {
+    let mathml = convert_to_mathml("$ f'(x) + g'' $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<msup><mi>f</mi><mo>′</mo></msup>"));
+    assert!(mathml.contains("<msup><mi>g</mi><mo>″</mo></msup>"));
+
+    let mathml = convert_to_mathml("$ x'_1 $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<msubsup><mi>x</mi><mn>1</mn><mo>′</mo></msubsup>"));
+}
+
+#[test]
+fn test_primes_with_superscript() {
+    let mathml = convert_to_mathml("$ f'^2 + f'_1^2 $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<msup><mi>f</mi><mrow><mo>′</mo><mn>2</mn></mrow></msup>"));
+    assert!(
+        mathml.contains("<msubsup><mi>f</mi><mn>1</mn><mrow><mo>′</mo><mn>2</mn></mrow></msubsup>")
+    );
+    assert!(!mathml.contains("mmultiscripts"));
+}
+
+#[test]
+fn test_many_primes() {
+    let mathml = convert_to_mathml("$ f'''' + f''''' + x #primes(2) $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mo>⁗</mo>"));
+    assert!(mathml.contains("<mo>′′′′′</mo>"));
+    assert!(mathml.contains("<msup><mi>x</mi><mo>″</mo></msup>"));
+}
@@ -0,0 +1,134 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, SpacingMode, TargetProfile},
+    mathml::convert_to_mathml,
+};
+
+fn typst_spacing(input: &str) -> String {
+    let converter = Converter::new(ConvertOptions {
+        spacing: SpacingMode::Typst,
+        ..Default::default()
+    });
+    let mathml = converter.convert(input).unwrap().mathml;
+    println!("{}", mathml);
+    mathml
+}
+
+#[test]
+fn test_class_spacing() {
+    let mathml = typst_spacing("$ a = b + c, d $");
+    assert!(mathml.contains(r#"lspace="0.278em" rspace="0.278em">=</mo>"#));
+    assert!(mathml.contains(r#"lspace="0.222em" rspace="0.222em">+</mo>"#));
+    assert!(mathml.contains(r#"lspace="0em" rspace="0.167em">,</mo>"#));
+
+    // Unary operators are not spaced.
+    let mathml = typst_spacing("$ -x $");
+    assert!(mathml.contains(r#"lspace="0em" rspace="0em">−</mo>"#));
+}
+
+#[test]
+fn test_script_spacing() {
+    // Binary operators and relations are not spaced in scripts.
+    let mathml = typst_spacing("$ x^(a + b) = 1 $");
+    assert!(mathml.contains(r#"lspace="0em" rspace="0em">+</mo>"#));
+    assert!(mathml.contains(r#"lspace="0.278em" rspace="0.278em">=</mo>"#));
+}
+
+#[test]
+fn test_renderer_spacing() {
+    let mathml = Converter::default().convert("$ a = b $").unwrap().mathml;
+    println!("{}", mathml);
+    assert!(!mathml.contains("lspace"));
+}
+
+#[test]
+fn test_h() {
+    let mathml = convert_to_mathml("$ a #h(1em) b #h(12pt) c #h(-0.5em) d #h(50%) e $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mspace width="1em"></mspace>"#));
+    // 12pt at the default text size of 11pt.
+    assert!(mathml.contains(r#"<mspace width="1.091em"></mspace>"#));
+    assert!(mathml.contains(r#"<mspace style="margin-left: -0.5em;"></mspace>"#));
+    assert_eq!(mathml.matches("<mspace").count(), 3);
+
+    let mathml = convert_to_mathml("$ a #h(1fr) b $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mspace class="equation-typst-fr" style="flex-grow: 1;"></mspace>"#));
+
+    // MathML has no fractional spacing, the marker stays and the loss is reported.
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let converted = converter.convert("$ a #h(1fr) b $").unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted
+        .mathml
+        .contains(r#"<mspace class="equation-typst-fr" width="0em"></mspace>"#));
+    assert_eq!(converted.unsupported.get("h.fr"), Some(&1));
+}
+
+#[test]
+fn test_source_spaces() {
+    // Spaces are kept around text, but not between atoms.
+    let mathml = convert_to_mathml(r#"$ x "if" y > 0 $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches(r#"<mspace width="0.333em">"#).count(), 2);
+
+    let mathml = convert_to_mathml(r#"$ x = "if" $"#, false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("<mspace"));
+}
+
+#[test]
+fn test_source_spaces_rules() {
+    // Spaces inside delimiters are kept like anywhere else.
+    let mathml = convert_to_mathml(r#"$ (x "if" y) $"#, false);
+    println!("{}", mathml);
+    assert_eq!(mathml.matches(r#"<mspace width="0.333em">"#).count(), 2);
+
+    // Rules without spacing, e.g. before punctuation or closing delimiters, drop
+    // the spaces of the source.
+    let mathml = convert_to_mathml(r#"$ ("if" , "else" ) $"#, false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("<mspace"));
+}
+
+#[test]
+fn test_function_spacing() {
+    // Named operators are large operators, spaced from their arguments unless
+    // delimiters follow.
+    let mathml = typst_spacing("$ 2 sin x + sin(x) $");
+    assert!(mathml.contains(r#"<mn>2</mn><mspace width="0.167em"></mspace><mi>sin</mi>"#));
+    assert!(mathml.contains(r#"&#x2061;</mo><mspace width="0.167em"></mspace><mi>x</mi>"#));
+    assert!(mathml.contains(r#"&#x2061;</mo><mrow><mo"#));
+}
+
+#[test]
+fn test_embellished_operator_spacing() {
+    // The spacing of an operator with attachments goes on its core operator.
+    let mathml = typst_spacing("$ a + sum_i x $");
+    assert!(mathml.contains(concat!(
+        r#"<mi>a</mi><mo form="infix" lspace="0.222em" rspace="0em">+</mo><munder>"#,
+        r#"<mo largeop="true" symmetric="true" movablelimits="true" lspace="0.222em" rspace="0.167em">∑</mo>"#,
+        r#"<mi>i</mi></munder><mi>x</mi>"#
+    )));
+
+    let mathml = typst_spacing("$ a =_x b $");
+    assert!(mathml.contains(concat!(
+        r#"<mi>a</mi><munder><mo movablelimits="false" lspace="0.278em" rspace="0.278em">=</mo>"#,
+        r#"<mi>x</mi></munder><mi>b</mi>"#
+    )));
+    assert!(!mathml.contains("<mspace"));
+}
+
+#[test]
+fn test_aligned_spacing() {
+    // Rows are spaced as a whole before they are split at alignment points.
+    let mathml = typst_spacing(r#"$ a &= b \ c &- d $"#);
+    assert!(mathml.contains(r#"<mo lspace="0.278em" rspace="0.278em">=</mo><mi>b</mi>"#));
+    assert!(
+        mathml.contains(r#"<mo form="infix" lspace="0.222em" rspace="0.222em">−</mo><mi>d</mi>"#)
+    );
+    assert!(!mathml.contains("prefix"));
+    assert!(!mathml.contains("malignmark"));
+}
@@ -0,0 +1,38 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    mathml::convert_to_mathml,
+};
+
+#[test]
+fn test_brace_and_bracket() {
+    let mathml = convert_to_mathml(
+        r#"$ underbrace(1 + 2, "sum") + overbracket(x, y + z) $"#,
+        false,
+    );
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<munder><munder accentunder="true"><mrow>"#));
+    assert!(mathml.contains(r#"<mo stretchy="true">⏟</mo></munder><mtext>sum</mtext></munder>"#));
+    assert!(mathml.contains(r#"<mover><mover accent="true"><mi>x</mi><mo stretchy="true">⎴</mo></mover><mrow><mi>y</mi>"#));
+    // Annotations are rows, not text.
+    assert!(!mathml.contains("<mtext><m"));
+}
+
+#[test]
+fn test_paren_and_shell() {
+    let input = "$ overparen(x y) + undershell(x, 1) $";
+    let mathml = convert_to_mathml(input, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mrow class="equation-typst-overparen"><mrow><mi>x</mi>"#));
+    assert!(mathml.contains(
+        r#"<munder><mrow class="equation-typst-undershell"><mi>x</mi></mrow><mn>1</mn></munder>"#
+    ));
+
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter.convert(input).unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mo stretchy="true">⏜</mo>"#));
+    assert!(mathml.contains(r#"<mo stretchy="true">⏡</mo>"#));
+}
@@ -0,0 +1,45 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    mathml::convert_to_mathml,
+};
+
+#[test]
+fn test_stretch_over_attachments() {
+    let mathml = convert_to_mathml(r#"$ a stretch(->)^"text" b $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(
+        r#"<mover><mo stretchy="true" movablelimits="false">→</mo><mtext>text</mtext></mover>"#
+    ));
+
+    let mathml = convert_to_mathml("$ x stretch(=)_(x -> 0)^(f(x)) y $", false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<munderover><mo stretchy="true" movablelimits="false">=</mo>"#));
+}
+
+#[test]
+fn test_stretch_size() {
+    let input = "$ a stretch(->, size: #200%) b $";
+    let mathml = convert_to_mathml(input, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(
+        r#"<mo stretchy="true" style="transform: scaleX(2); margin: 0 0.5em">→</mo>"#
+    ));
+
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter.convert(input).unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mo stretchy="true" minsize="200%" maxsize="200%">→</mo>"#));
+}
+
+#[test]
+fn test_stretch_size_with_attachments() {
+    // The scale of the CSS fallback assumes the width of the glyph, not of the
+    // attachments the operator stretches over.
+    let mathml = convert_to_mathml(r#"$ a stretch(->, size: #200%)^"text" b $"#, false);
+    println!("{}", mathml);
+    assert!(!mathml.contains("scaleX"));
+    assert!(mathml.contains(r#"<mover><mo stretchy="true" minsize="200%" maxsize="200%""#));
+}
@@ -0,0 +1,44 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, TargetProfile},
+    mathml::convert_to_mathml,
+};
+
+#[test]
+fn test_strings() {
+    let mathml = convert_to_mathml(r#"$ "area" = pi r^2 $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mtext>area</mtext>"));
+
+    // Spaces of the string are kept, spaces around it only next to other atoms.
+    let mathml = convert_to_mathml(r#"$ x " if  so " y $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mtext>&#xA0;if &#xA0;so&#xA0;</mtext>"));
+    assert!(mathml.contains(r#"<mi>x</mi><mspace width="0.333em"></mspace><mtext>"#));
+
+    // Strings of a single character are text as well.
+    let mathml = convert_to_mathml(r#"$ "a" + b $"#, false);
+    println!("{}", mathml);
+    assert!(mathml.contains("<mtext>a</mtext>"));
+    assert!(mathml.contains("<mi>b</mi>"));
+}
+
+#[test]
+fn test_markup() {
+    let input = r#"$ #strong[x] + #emph("text") + #smallcaps("Typst") + #raw("a + b") $"#;
+    let mathml = convert_to_mathml(input, false);
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mi style="font-weight: bold">x</mi>"#));
+    assert!(mathml.contains(r#"<mtext style="font-style: italic">text</mtext>"#));
+    assert!(mathml.contains(r#"<mtext style="font-variant: small-caps">Typst</mtext>"#));
+    assert!(mathml.contains(r#"<mtext style="font-family: monospace">a + b</mtext>"#));
+
+    let converter = Converter::new(ConvertOptions {
+        profile: TargetProfile::Full,
+        ..Default::default()
+    });
+    let mathml = converter.convert(input).unwrap().mathml;
+    println!("{}", mathml);
+    assert!(mathml.contains(r#"<mi mathvariant="bold">x</mi>"#));
+    assert!(mathml.contains(r#"<mtext mathvariant="italic">text</mtext>"#));
+    assert!(mathml.contains(r#"<mtext mathvariant="monospace">a + b</mtext>"#));
+}
@@ -0,0 +1,78 @@
+use convert_typst_math_to_mathml::{
+    converter::{ConvertOptions, Converter, UnknownElementCallback, UnknownElementPolicy},
+    diagnostic::ConvertError,
+    node::Element,
+};
+
+const INPUT: &str = "$ a + #rect() + #rect() $";
+
+fn converter(policy: UnknownElementPolicy) -> Converter {
+    Converter::new(ConvertOptions {
+        unknown_element: policy,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_merror() {
+    let converted = converter(UnknownElementPolicy::Merror)
+        .convert(INPUT)
+        .unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted
+        .mathml
+        .contains("<merror>`rect` Not Implemented Yet</merror>"));
+    assert_eq!(converted.unsupported.get("rect"), Some(&2));
+}
+
+#[test]
+fn test_error() {
+    let err = converter(UnknownElementPolicy::Error)
+        .convert(INPUT)
+        .unwrap_err();
+    println!("{}", err);
+
+    let ConvertError::UnsupportedElement { name, location } = err else {
+        panic!("expected an unsupported element error");
+    };
+    assert_eq!(name, "rect");
+    let location = location.unwrap();
+    assert_eq!(&INPUT[location.range.clone()], "rect()");
+    assert_eq!((location.start.line, location.start.column), (0, 7));
+}
+
+#[test]
+fn test_skip() {
+    let converted = converter(UnknownElementPolicy::Skip)
+        .convert(INPUT)
+        .unwrap();
+    println!("{}", converted.mathml);
+    assert!(!converted.mathml.contains("merror"));
+    assert_eq!(converted.unsupported.get("rect"), Some(&2));
+}
+
+#[test]
+fn test_callback() {
+    let callback = UnknownElementCallback::new(|content| {
+        Some(
+            Element::new("mtext")
+                .with_text(format!("[{}]", content.elem().name()))
+                .into(),
+        )
+    });
+    let converted = converter(UnknownElementPolicy::Callback(callback))
+        .convert(INPUT)
+        .unwrap();
+    println!("{}", converted.mathml);
+    assert!(converted.mathml.contains("<mtext>[rect]</mtext>"));
+}
+
+#[test]
+fn test_document_summary() {
+    let document = converter(UnknownElementPolicy::Skip)
+        .convert_all("$#rect()$ and $ x + #circle() + #rect() $")
+        .unwrap();
+    assert_eq!(document.equations.len(), 2);
+    assert_eq!(document.unsupported.get("rect"), Some(&2));
+    assert_eq!(document.unsupported.get("circle"), Some(&1));
+}